@@ -0,0 +1,94 @@
+use herpooles::replay::{Mode, Submission};
+use herpooles::scores::MAX_NAME;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::store::{Record, Store};
+
+// Largest request body taken, a long run is well under it.
+pub const MAX_BODY: usize = 1024 * 1024;
+// Scores returned when the query does not say.
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+pub struct Response {
+    pub status: u16,
+    // JSON
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: impl Serialize) -> Response {
+        Response {
+            status,
+            body: serde_json::to_string(&value).expect("serialize"),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, json!({ "error": message }))
+    }
+}
+
+// The REST API, apart from the HTTP server:
+// POST /api/scores            a Submission, answers {"rank": n} once the replay checks out
+// GET  /api/scores?mode=&limit= the best runs of a mode, classic and 10 by default
+// now is in seconds since the epoch.
+pub fn handle(store: &mut Store, method: &str, url: &str, body: &str, now: u64) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    match (method, path) {
+        ("POST", "/api/scores") => submit(store, body, now),
+        ("GET", "/api/scores") => scores(store, query),
+        (_, "/api/scores") => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn submit(store: &mut Store, body: &str, now: u64) -> Response {
+    let submission: Submission = match serde_json::from_str(body) {
+        Ok(submission) => submission,
+        Err(e) => return Response::error(400, &e.to_string()),
+    };
+    let name = submission.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME {
+        return Response::error(422, &format!("name must be 1 to {} characters", MAX_NAME));
+    }
+    let outcome = match submission.verify() {
+        Ok(outcome) => outcome,
+        Err(e) => return Response::error(422, &e),
+    };
+    let record = Record {
+        name: name.to_string(),
+        score: outcome.score,
+        level: outcome.level,
+        ticks: outcome.ticks,
+        date: now,
+    };
+    match store.insert(submission.mode, record) {
+        Ok(rank) => Response::json(201, json!({ "rank": rank })),
+        Err(e) => {
+            eprintln!("could not store a score: {}", e);
+            Response::error(500, "could not store the score")
+        }
+    }
+}
+
+fn scores(store: &Store, query: &str) -> Response {
+    let mut mode = Mode::Classic;
+    let mut limit = DEFAULT_LIMIT;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "mode" => match serde_json::from_value(json!(value)) {
+                Ok(m) => mode = m,
+                Err(_) => return Response::error(400, "unknown mode"),
+            },
+            "limit" => match value.parse::<usize>() {
+                Ok(l) => limit = l.min(MAX_LIMIT),
+                Err(_) => return Response::error(400, "limit must be a number"),
+            },
+            _ => {}
+        }
+    }
+    Response::json(200, store.top(mode, limit))
+}
@@ -0,0 +1,24 @@
+use std::net::TcpListener;
+
+const USAGE: &str = "usage: herpooles-rooms [--addr 127.0.0.1:8788]";
+
+fn main() {
+    let mut addr = "127.0.0.1:8788".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let listener = TcpListener::bind(&addr).unwrap_or_else(|e| {
+        eprintln!("could not listen on {}: {}", addr, e);
+        std::process::exit(1);
+    });
+    println!("rooms on ws://{}", addr);
+    herpooles_server::rooms::serve(listener);
+}
@@ -0,0 +1,11 @@
+//! The herpooles servers. The scoreboard keeps the best runs of every game mode and only
+//! takes a score once its replay, played again with the game's own update, comes to it.
+//! The room server runs online co-op games and streams them to the players, or only passes
+//! their inputs around for rollback play, and passes games played alone on to whoever
+//! watches them.
+pub mod api;
+pub mod relay;
+pub mod room;
+pub mod rooms;
+pub mod stage;
+pub mod store;
@@ -0,0 +1,80 @@
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use herpooles_server::api::{self, MAX_BODY};
+use herpooles_server::store::Store;
+use tiny_http::{Header, Method, Response, Server};
+
+const USAGE: &str = "usage: herpooles-server [--addr 127.0.0.1:8787] [--data scoreboard]";
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn main() {
+    let mut addr = "127.0.0.1:8787".to_string();
+    let mut data = "scoreboard".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            ("--data", Some(value)) => data = value,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut store = Store::open(&data).unwrap_or_else(|e| {
+        eprintln!("could not open {}: {}", data, e);
+        std::process::exit(1);
+    });
+    let server = Server::http(&addr).unwrap_or_else(|e| {
+        eprintln!("could not listen on {}: {}", addr, e);
+        std::process::exit(1);
+    });
+    println!("scoreboard on http://{}, scores in {}", addr, data);
+
+    for mut request in server.incoming_requests() {
+        // the game is served from another origin
+        let cors = [
+            header("Access-Control-Allow-Origin", "*"),
+            header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
+            header("Access-Control-Allow-Headers", "Content-Type"),
+        ];
+        let response = if *request.method() == Method::Options {
+            api::Response {
+                status: 204,
+                body: String::new(),
+            }
+        } else {
+            let mut body = String::new();
+            let read = request
+                .as_reader()
+                .take(MAX_BODY as u64 + 1)
+                .read_to_string(&mut body);
+            if read.is_err() || body.len() > MAX_BODY {
+                api::Response {
+                    status: 413,
+                    body: r#"{"error":"request too large or not text"}"#.to_string(),
+                }
+            } else {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                let method = request.method().to_string();
+                api::handle(&mut store, &method, request.url(), &body, now)
+            }
+        };
+        let mut reply = Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(header("Content-Type", "application/json"));
+        for h in cors {
+            reply.add_header(h);
+        }
+        if let Err(e) = request.respond(reply) {
+            eprintln!("could not answer: {}", e);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use herpooles::net::{MAX_ROOM_PLAYERS, MIN_ROOM_PLAYERS};
+
+// A rollback room: the server does not play the game, it only seats the players, gives them
+// a seed once they are all in and passes their inputs around. C is how the server tells
+// its clients apart.
+pub struct Relay<C> {
+    members: Vec<Option<C>>,
+    seed: u64,
+    started: bool,
+}
+
+impl<C: Copy + PartialEq> Relay<C> {
+    pub fn new(players: usize) -> Relay<C> {
+        Relay {
+            members: vec![None; players.clamp(MIN_ROOM_PLAYERS, MAX_ROOM_PLAYERS)],
+            seed: rand::random(),
+            started: false,
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.iter().all(Option::is_none)
+    }
+
+    // Takes the first free seat and returns its player. The game is for the players it
+    // started with, nobody comes in after, not even in the seat of one who left.
+    pub fn join(&mut self, client: C) -> Option<usize> {
+        if self.started {
+            return None;
+        }
+        let player = self.members.iter().position(Option::is_none)?;
+        self.members[player] = Some(client);
+        Some(player)
+    }
+
+    // True once, when the last player came in.
+    pub fn start(&mut self) -> bool {
+        if self.started || self.members.iter().any(Option::is_none) {
+            return false;
+        }
+        self.started = true;
+        true
+    }
+
+    pub fn leave(&mut self, player: usize) {
+        self.members[player] = None;
+    }
+
+    // The clients of the room but the one of player.
+    pub fn others(&self, player: usize) -> impl Iterator<Item = C> + '_ {
+        self.members
+            .iter()
+            .enumerate()
+            .filter(move |(p, _)| *p != player)
+            .filter_map(|(_, c)| *c)
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = C> + '_ {
+        self.members.iter().filter_map(|c| *c)
+    }
+}
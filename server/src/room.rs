@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+use herpooles::game::{self, Controller, Event, Herpooles, Input, Rules};
+use herpooles::net::{State, MAX_ROOM_PLAYERS, MIN_ROOM_PLAYERS};
+
+// Inputs of a player waiting to be played. A client further ahead than this has its
+// oldest inputs dropped, so a stall does not leave it behind for good.
+const MAX_QUEUE: usize = 10;
+// Ticks between the last hero going down and the next run.
+const RESTART_TICKS: u64 = 3 * 60;
+
+struct Seat {
+    inputs: VecDeque<(u64, Input)>,
+    // played again while no input comes in, without the fire
+    last: Input,
+    ack: u64,
+}
+
+// One game played by up to four players, stepped by the server at the rate of the game.
+// The simulation is the game's own update, each client only sends the input of its hero.
+pub struct Room {
+    seats: [Option<Seat>; MAX_ROOM_PLAYERS],
+    heroes: Vec<Herpooles>,
+    controller: Controller,
+    tick: u64,
+    // the run only starts once enough players are in, then goes on whoever leaves
+    started: bool,
+    over_since: Option<u64>,
+    // of the ticks since the states last went out
+    events: Vec<Event>,
+}
+
+impl Default for Room {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Room {
+    pub fn new() -> Room {
+        Room {
+            seats: Default::default(),
+            heroes: vec![],
+            controller: Controller::new(MIN_ROOM_PLAYERS, Rules::Coop),
+            tick: 0,
+            started: false,
+            over_since: None,
+            events: vec![],
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.seats.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players() == 0
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        !self.started
+    }
+
+    pub fn heroes(&self) -> &[Herpooles] {
+        &self.heroes
+    }
+
+    pub fn controller(&self) -> &Controller {
+        &self.controller
+    }
+
+    // Takes the first free seat and returns its player, None when the room is full.
+    // A player joining a run in progress comes in with a fresh hero.
+    pub fn join(&mut self) -> Option<usize> {
+        let player = self.seats.iter().position(Option::is_none)?;
+        self.seats[player] = Some(Seat {
+            inputs: VecDeque::new(),
+            last: Input::capture(&Herpooles::new(), Default::default()),
+            ack: 0,
+        });
+        self.add_hero(player);
+        if self.players() >= MIN_ROOM_PLAYERS {
+            self.started = true;
+        }
+        Some(player)
+    }
+
+    // The hero leaves with its player, its kills stay in the score of the run.
+    pub fn leave(&mut self, player: usize) {
+        self.seats[player] = None;
+        self.heroes.retain(|h| h.player != player);
+    }
+
+    pub fn input(&mut self, player: usize, seq: u64, input: Input) {
+        let Some(seat) = self.seats[player].as_mut() else {
+            return;
+        };
+        // late or repeated
+        if seq <= seat.inputs.back().map_or(seat.ack, |(s, _)| *s) {
+            return;
+        }
+        seat.inputs.push_back((seq, input));
+        while seat.inputs.len() > MAX_QUEUE {
+            if let Some((seq, _)) = seat.inputs.pop_front() {
+                seat.ack = seq;
+            }
+        }
+    }
+
+    // Plays one tick: one input of every player, or the last one again if none came.
+    pub fn step(&mut self) {
+        self.tick += 1;
+        if !self.started {
+            self.drain();
+            return;
+        }
+        if game::all_gone(&self.heroes) {
+            self.drain();
+            let since = *self.over_since.get_or_insert(self.tick);
+            if self.tick - since >= RESTART_TICKS {
+                self.restart();
+            }
+            return;
+        }
+        let mut inputs = vec![];
+        for h in self.heroes.iter() {
+            let seat = self.seats[h.player].as_mut().expect("a hero has a seat");
+            let input = match seat.inputs.pop_front() {
+                Some((seq, input)) => {
+                    seat.ack = seq;
+                    seat.last = input;
+                    input
+                }
+                None => Input {
+                    fire: false,
+                    ..seat.last
+                },
+            };
+            inputs.push(input);
+        }
+        let events = game::tick(&mut self.heroes, &inputs, &mut self.controller);
+        self.events.extend(events);
+    }
+
+    // The room as the player gets it.
+    pub fn state(&self, player: usize) -> State {
+        State {
+            tick: self.tick,
+            ack: self.seats[player].as_ref().map_or(0, |s| s.ack),
+            waiting: !self.started,
+            heroes: self.heroes.clone(),
+            controller: self.controller.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    // The states went out to every player, their events are not sent again.
+    pub fn states_sent(&mut self) {
+        self.events.clear();
+    }
+
+    // Nothing to play, the inputs are taken so the clients stop predicting them.
+    fn drain(&mut self) {
+        for seat in self.seats.iter_mut().flatten() {
+            if let Some((seq, _)) = seat.inputs.drain(..).next_back() {
+                seat.ack = seq;
+            }
+        }
+    }
+
+    fn restart(&mut self) {
+        self.controller = Controller::new(self.players(), Rules::Coop);
+        self.heroes.clear();
+        let seated: Vec<usize> = (0..MAX_ROOM_PLAYERS)
+            .filter(|p| self.seats[*p].is_some())
+            .collect();
+        for player in seated {
+            self.add_hero(player);
+        }
+        self.over_since = None;
+    }
+
+    // Heroes keep their place in the line of four, in the order of their players.
+    fn add_hero(&mut self, player: usize) {
+        let hero = Herpooles::team(MAX_ROOM_PLAYERS).swap_remove(player);
+        let at = self.heroes.partition_point(|h| h.player < player);
+        self.heroes.insert(at, hero);
+    }
+}
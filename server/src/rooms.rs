@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use herpooles::game::TICK;
+use herpooles::net::{
+    ClientMessage, ServerMessage, MAX_ROOM_PLAYERS, MIN_ROOM_PLAYERS, STATE_INTERVAL,
+};
+use tungstenite::{Error, Message};
+
+use crate::relay::Relay;
+use crate::room::Room;
+use crate::stage::Stage;
+
+// How long a connection waits for a message before it looks for states to send.
+const POLL: Duration = Duration::from_millis(2);
+
+enum Event {
+    Connected(Sender<String>),
+    Message(ClientMessage),
+    Gone,
+}
+
+// Where a client plays, by room name and player, or broadcasts or watches.
+enum Seat {
+    Room(String, usize),
+    Relay(String, usize),
+    Stage(String),
+}
+
+struct Client {
+    // the text frames to send, the connection thread writes them
+    outbox: Sender<String>,
+    seat: Option<Seat>,
+}
+
+// The rooms of the server, run by one thread at the rate of the game. The connections
+// only pass messages in and out, every room is in here.
+struct Hub {
+    clients: HashMap<u64, Client>,
+    rooms: HashMap<String, Room>,
+    relays: HashMap<String, Relay<u64>>,
+    stages: HashMap<String, Stage<u64>>,
+}
+
+impl Hub {
+    fn send(&self, id: u64, message: &ServerMessage) {
+        if let Some(client) = self.clients.get(&id) {
+            let _ = client.outbox.send(message.to_json());
+        }
+    }
+
+    fn refuse(&self, id: u64, reason: &str) {
+        let reason = reason.to_string();
+        self.send(id, &ServerMessage::Refused { reason });
+    }
+
+    // A client sits down once, and only while it is connected.
+    fn can_sit(&self, id: u64) -> bool {
+        self.clients.get(&id).is_some_and(|c| c.seat.is_none())
+    }
+
+    fn handle(&mut self, id: u64, event: Event) {
+        match event {
+            Event::Connected(outbox) => {
+                self.clients.insert(id, Client { outbox, seat: None });
+            }
+            // nothing is touched for a client that sits somewhere already
+            Event::Message(
+                ClientMessage::Join { .. }
+                | ClientMessage::JoinRollback { .. }
+                | ClientMessage::Broadcast { .. }
+                | ClientMessage::Watch { .. },
+            ) if !self.can_sit(id) => self.refuse(id, "already in a room"),
+            Event::Message(ClientMessage::Join { room }) => {
+                let seat = self
+                    .rooms
+                    .entry(room.clone())
+                    .or_default()
+                    .join()
+                    .map(|player| Seat::Room(room, player));
+                self.seat(id, seat, "the room is full");
+            }
+            Event::Message(ClientMessage::JoinRollback { room, players }) => {
+                if !(MIN_ROOM_PLAYERS..=MAX_ROOM_PLAYERS).contains(&players) {
+                    let reason = format!(
+                        "a game is for {} to {} players",
+                        MIN_ROOM_PLAYERS, MAX_ROOM_PLAYERS
+                    );
+                    self.refuse(id, &reason);
+                    return;
+                }
+                let relay = self
+                    .relays
+                    .entry(room.clone())
+                    .or_insert_with(|| Relay::new(players));
+                // the first to join picks the players
+                if relay.players() != players {
+                    let reason = format!("the game is for {} players", relay.players());
+                    self.refuse(id, &reason);
+                    return;
+                }
+                let seat = relay.join(id).map(|player| Seat::Relay(room, player));
+                self.seat(id, seat, "the game is full or has started");
+            }
+            Event::Message(ClientMessage::Input { seq, input }) => {
+                let seat = self.clients.get(&id).and_then(|c| c.seat.as_ref());
+                if let Some(Seat::Room(name, player)) = seat {
+                    if let Some(room) = self.rooms.get_mut(name) {
+                        room.input(*player, seq, input);
+                    }
+                }
+            }
+            Event::Message(ClientMessage::Inputs(mut inputs)) => {
+                let seat = self.clients.get(&id).and_then(|c| c.seat.as_ref());
+                if let Some(Seat::Relay(name, player)) = seat {
+                    // one ack per player of the game, or the message is dropped
+                    if inputs.acks.len() != self.relays[name].players() {
+                        return;
+                    }
+                    // a client only speaks for its own hero
+                    inputs.player = *player;
+                    let message = ServerMessage::Inputs(inputs);
+                    for other in self.relays[name].others(*player) {
+                        self.send(other, &message);
+                    }
+                }
+            }
+            Event::Message(ClientMessage::Broadcast { room }) => {
+                let stage = self.stages.entry(room.clone()).or_default();
+                let seat = stage.broadcast(id).then_some(Seat::Stage(room));
+                self.seat(id, seat, "somebody broadcasts in the room already");
+            }
+            Event::Message(ClientMessage::Watch { room }) => {
+                let stage = self.stages.entry(room.clone()).or_default();
+                let seat = stage.watch(id).then(|| Seat::Stage(room.clone()));
+                if self.seat(id, seat, "the room has enough watchers") {
+                    // the run so far, the ticks that follow come as they are played
+                    if let Some(run) = self.stages.get(&room).and_then(Stage::run) {
+                        self.send(id, &ServerMessage::Ticks(run));
+                    }
+                }
+            }
+            Event::Message(ClientMessage::Ticks(ticks)) => {
+                let seat = self.clients.get(&id).and_then(|c| c.seat.as_ref());
+                if let Some(Seat::Stage(name)) = seat {
+                    let stage = self.stages.get_mut(name).expect("stage of a seat");
+                    if stage.receive(id, &ticks) {
+                        let message = ServerMessage::Ticks(ticks);
+                        for watcher in stage.watchers() {
+                            if let Some(client) = self.clients.get(&watcher) {
+                                let _ = client.outbox.send(message.to_json());
+                            }
+                        }
+                    }
+                }
+            }
+            Event::Gone => self.leave(id),
+        }
+    }
+
+    // Seats a client that can sit, see can_sit, or tells it the place is full. True if it
+    // was seated.
+    fn seat(&mut self, id: u64, seat: Option<Seat>, full: &str) -> bool {
+        let Some(seat) = seat else {
+            self.refuse(id, full);
+            return false;
+        };
+        let (player, relay) = match &seat {
+            Seat::Room(_, player) => (Some(*player), None),
+            Seat::Relay(name, player) => (Some(*player), Some(name.clone())),
+            // broadcasters and watchers play no hero
+            Seat::Stage(_) => (None, None),
+        };
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.seat = Some(seat);
+        }
+        if let Some(player) = player {
+            self.send(id, &ServerMessage::Welcome { player });
+        }
+        let Some(relay) = relay.and_then(|name| self.relays.get_mut(&name)) else {
+            return true;
+        };
+        if relay.start() {
+            let start = ServerMessage::Start {
+                seed: relay.seed(),
+                players: relay.players(),
+            };
+            let clients: Vec<u64> = relay.clients().collect();
+            for client in clients {
+                self.send(client, &start);
+            }
+        }
+        true
+    }
+
+    fn leave(&mut self, id: u64) {
+        if let Some(seat) = self.clients.remove(&id).and_then(|c| c.seat) {
+            self.vacate(id, seat);
+        }
+    }
+
+    fn vacate(&mut self, id: u64, seat: Seat) {
+        match seat {
+            Seat::Room(name, player) => {
+                if let Some(room) = self.rooms.get_mut(&name) {
+                    room.leave(player);
+                    if room.is_empty() {
+                        self.rooms.remove(&name);
+                    }
+                }
+            }
+            Seat::Relay(name, player) => {
+                if let Some(relay) = self.relays.get_mut(&name) {
+                    relay.leave(player);
+                    let left = ServerMessage::Left { player };
+                    for other in relay.clients() {
+                        if let Some(client) = self.clients.get(&other) {
+                            let _ = client.outbox.send(left.to_json());
+                        }
+                    }
+                    if relay.is_empty() {
+                        self.relays.remove(&name);
+                    }
+                }
+            }
+            Seat::Stage(name) => {
+                if let Some(stage) = self.stages.get_mut(&name) {
+                    if stage.leave(id) {
+                        for watcher in stage.watchers() {
+                            if let Some(client) = self.clients.get(&watcher) {
+                                let _ = client.outbox.send(ServerMessage::Stopped.to_json());
+                            }
+                        }
+                    }
+                    if stage.is_empty() {
+                        self.stages.remove(&name);
+                    }
+                }
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        for room in self.rooms.values_mut() {
+            room.step();
+        }
+    }
+
+    fn broadcast(&mut self) {
+        for client in self.clients.values() {
+            if let Some(Seat::Room(name, player)) = &client.seat {
+                let state = ServerMessage::State(Box::new(self.rooms[name].state(*player)));
+                let _ = client.outbox.send(state.to_json());
+            }
+        }
+        for room in self.rooms.values_mut() {
+            room.states_sent();
+        }
+    }
+}
+
+fn run_hub(events: Receiver<(u64, Event)>) {
+    let mut hub = Hub {
+        clients: HashMap::new(),
+        rooms: HashMap::new(),
+        relays: HashMap::new(),
+        stages: HashMap::new(),
+    };
+    let tick = Duration::from_secs_f64(TICK);
+    let mut next = Instant::now();
+    for ticks in 0u64.. {
+        loop {
+            match events.try_recv() {
+                Ok((id, event)) => hub.handle(id, event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        hub.step();
+        if ticks.is_multiple_of(STATE_INTERVAL) {
+            hub.broadcast();
+        }
+        next += tick;
+        // a hub that fell behind catches up rather than sleeping
+        if let Some(wait) = next.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+fn is_timeout(e: &Error) -> bool {
+    matches!(e, Error::Io(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut))
+}
+
+// One thread per connection: messages from the socket go to the hub, states from the hub
+// go to the socket. Messages that do not parse are dropped.
+fn connection(id: u64, stream: TcpStream, events: Sender<(u64, Event)>) -> Result<(), Box<Error>> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(e) => e,
+        tungstenite::HandshakeError::Interrupted(_) => Error::ConnectionClosed,
+    })?;
+    socket.get_ref().set_nodelay(true).map_err(Error::Io)?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(POLL))
+        .map_err(Error::Io)?;
+    let (outbox, inbox) = mpsc::channel();
+    let _ = events.send((id, Event::Connected(outbox)));
+    let result = 'connection: loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Ok(message) = serde_json::from_str(&text) {
+                    let _ = events.send((id, Event::Message(message)));
+                }
+            }
+            Ok(Message::Close(_)) => break Ok(()),
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => {}
+            Err(e) => break Err(e),
+        }
+        for text in inbox.try_iter() {
+            if let Err(e) = socket.send(Message::Text(text)) {
+                break 'connection Err(e);
+            }
+        }
+    };
+    let _ = events.send((id, Event::Gone));
+    match result {
+        Ok(()) | Err(Error::ConnectionClosed | Error::AlreadyClosed) => Ok(()),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+// Serves the rooms on the listener, never returns. Players join a room by name, the first
+// one to join opens it. Broadcast rooms are by name too, apart from the others.
+pub fn serve(listener: TcpListener) {
+    let (events, hub_events) = mpsc::channel();
+    thread::spawn(move || run_hub(hub_events));
+    for (id, stream) in (0u64..).zip(listener.incoming()) {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("could not accept: {}", e);
+                continue;
+            }
+        };
+        let events = events.clone();
+        thread::spawn(move || {
+            if let Err(e) = connection(id, stream, events) {
+                eprintln!("connection {}: {}", id, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,87 @@
+use herpooles::replay::{Replay, MAX_TICKS};
+use herpooles::spectate::Ticks;
+
+// Watchers of one broadcast room.
+pub const MAX_WATCHERS: usize = 16;
+
+// A broadcast room: one client plays a run on its own and sends its ticks as it goes, the
+// others watch. The server keeps the run so far for the watchers who come in late. C is how
+// the server tells its clients apart.
+pub struct Stage<C> {
+    broadcaster: Option<C>,
+    watchers: Vec<C>,
+    run: Option<Replay>,
+}
+
+impl<C: Copy + PartialEq> Default for Stage<C> {
+    fn default() -> Self {
+        Stage {
+            broadcaster: None,
+            watchers: vec![],
+            run: None,
+        }
+    }
+}
+
+impl<C: Copy + PartialEq> Stage<C> {
+    pub fn is_empty(&self) -> bool {
+        self.broadcaster.is_none() && self.watchers.is_empty()
+    }
+
+    // False if somebody broadcasts in the room already.
+    pub fn broadcast(&mut self, client: C) -> bool {
+        if self.broadcaster.is_some() {
+            return false;
+        }
+        self.broadcaster = Some(client);
+        true
+    }
+
+    // False if the room has as many watchers as it takes.
+    pub fn watch(&mut self, client: C) -> bool {
+        if self.watchers.len() >= MAX_WATCHERS {
+            return false;
+        }
+        self.watchers.push(client);
+        true
+    }
+
+    // The run so far, for a new watcher.
+    pub fn run(&self) -> Option<Ticks> {
+        self.run.clone().map(|replay| Ticks { first: 0, replay })
+    }
+
+    // Keeps the ticks if they come from the broadcaster and follow the run so far, or start
+    // another run. True when they are to go on to the watchers.
+    pub fn receive(&mut self, client: C, ticks: &Ticks) -> bool {
+        if self.broadcaster != Some(client) {
+            return false;
+        }
+        let replay = &ticks.replay;
+        match &mut self.run {
+            _ if ticks.first == 0 => self.run = Some(replay.clone()),
+            Some(run) if run.same_run(replay) && run.ticks() == ticks.first => run.append(replay),
+            _ => return false,
+        }
+        // an endless broadcast is cut off like an endless replay
+        if self.run.as_ref().is_some_and(|run| run.ticks() > MAX_TICKS) {
+            self.run = None;
+            return false;
+        }
+        true
+    }
+
+    // True if the client was the broadcaster, the watchers are told then.
+    pub fn leave(&mut self, client: C) -> bool {
+        self.watchers.retain(|w| *w != client);
+        if self.broadcaster == Some(client) {
+            self.broadcaster = None;
+            return true;
+        }
+        false
+    }
+
+    pub fn watchers(&self) -> impl Iterator<Item = C> + '_ {
+        self.watchers.iter().copied()
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use herpooles::replay::Mode;
+use serde::{Deserialize, Serialize};
+
+// A verified run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub name: String,
+    pub score: u32,
+    pub level: u16,
+    // simulation ticks the run lasted
+    pub ticks: u64,
+    // seconds since the epoch the run was submitted at
+    pub date: u64,
+}
+
+// The scoreboards as one JSON lines file per mode in a directory, <mode>.jsonl. Records are
+// only ever appended, the files are read back once on start.
+pub struct Store {
+    dir: PathBuf,
+    // best score first, among equal scores the older run first
+    boards: HashMap<Mode, Vec<Record>>,
+}
+
+impl Store {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Store> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let mut store = Store {
+            dir,
+            boards: HashMap::new(),
+        };
+        for mode in Mode::ALL {
+            let path = store.path(mode);
+            if !path.exists() {
+                continue;
+            }
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                match serde_json::from_str(&line) {
+                    Ok(record) => {
+                        store.rank(mode, record);
+                    }
+                    // a line cut short by a crash, the rest is still good
+                    Err(e) => eprintln!("skipping a record in {}: {}", path.display(), e),
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    fn path(&self, mode: Mode) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", mode.name()))
+    }
+
+    fn rank(&mut self, mode: Mode, record: Record) -> usize {
+        let board = self.boards.entry(mode).or_default();
+        let rank = board.partition_point(|r| r.score >= record.score);
+        board.insert(rank, record);
+        rank
+    }
+
+    // Keeps a run and returns its place on the board, 0 for the best.
+    pub fn insert(&mut self, mode: Mode, record: Record) -> io::Result<usize> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(mode))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(self.rank(mode, record))
+    }
+
+    pub fn top(&self, mode: Mode, limit: usize) -> &[Record] {
+        let board = self.boards.get(&mode).map_or(&[][..], Vec::as_slice);
+        &board[..limit.min(board.len())]
+    }
+}
@@ -0,0 +1,97 @@
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use std::path::PathBuf;
+
+use herpooles::game::{Controller, Rules};
+use herpooles::replay::{Mode, Submission};
+use herpooles::PressedKeys;
+use herpooles_server::api::handle;
+use herpooles_server::store::Store;
+
+fn data_dir(test: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("herpooles-server-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// A run where herpooles stands still and fires now and then until the zombies get him.
+fn run(seed: u64, name: &str) -> Submission {
+    let controller = Controller::with_seed(seed, 1, Rules::Coop);
+    let (controller, _) = common::play(controller, |tick, h| {
+        if tick.is_multiple_of(45) {
+            h.fire_poo();
+        }
+        PressedKeys::default()
+    });
+    Submission {
+        mode: Mode::Classic,
+        name: name.to_string(),
+        score: controller.score,
+        level: controller.level(),
+        replay: controller.replay,
+    }
+}
+
+#[test]
+fn verified_runs_are_kept_across_restarts() {
+    let dir = data_dir("kept");
+    let mut store = Store::open(&dir).unwrap();
+    let first = run(1, "ann");
+    let body = serde_json::to_string(&first).unwrap();
+    let response = handle(&mut store, "POST", "/api/scores", &body, 100);
+    assert_eq!(response.status, 201, "{}", response.body);
+    assert_eq!(response.body, r#"{"rank":0}"#);
+
+    let mut store = Store::open(&dir).unwrap();
+    let response = handle(
+        &mut store,
+        "GET",
+        "/api/scores?mode=classic&limit=5",
+        "",
+        200,
+    );
+    assert_eq!(response.status, 200);
+    let scores: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(scores[0]["name"], "ann");
+    assert_eq!(scores[0]["score"], first.score);
+    assert_eq!(scores[0]["date"], 100);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn forged_and_malformed_submissions_are_refused() {
+    let dir = data_dir("refused");
+    let mut store = Store::open(&dir).unwrap();
+    let mut forged = run(2, "bob");
+    forged.score += 10;
+    let body = serde_json::to_string(&forged).unwrap();
+    assert_eq!(
+        handle(&mut store, "POST", "/api/scores", &body, 0).status,
+        422
+    );
+
+    let unnamed = run(2, " ");
+    let body = serde_json::to_string(&unnamed).unwrap();
+    assert_eq!(
+        handle(&mut store, "POST", "/api/scores", &body, 0).status,
+        422
+    );
+
+    assert_eq!(
+        handle(&mut store, "POST", "/api/scores", "{", 0).status,
+        400
+    );
+    assert_eq!(
+        handle(&mut store, "GET", "/api/scores?mode=nope", "", 0).status,
+        400
+    );
+    assert_eq!(
+        handle(&mut store, "DELETE", "/api/scores", "", 0).status,
+        405
+    );
+    assert_eq!(handle(&mut store, "GET", "/", "", 0).status, 404);
+    assert_eq!(store.top(Mode::Classic, 10), &[]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,444 @@
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use herpooles::game::{Direction, Event, Input, TICK};
+use herpooles::net::{ClientMessage, ServerMessage, Session, State, MAX_ROOM_PLAYERS};
+use herpooles::replay::Replay;
+use herpooles::rollback::Rollback;
+use herpooles::spectate::{Broadcast, Spectator, Ticks};
+use herpooles::PressedKeys;
+use herpooles_server::room::Room;
+use herpooles_server::rooms::serve;
+use herpooles_server::stage::{Stage, MAX_WATCHERS};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+fn walking_right() -> Input {
+    Input {
+        keys: PressedKeys {
+            right: true,
+            ..PressedKeys::default()
+        },
+        fire: false,
+        bearing: Direction::North,
+    }
+}
+
+#[test]
+fn rooms_seat_four_and_play_their_inputs() {
+    let mut room = Room::new();
+    assert_eq!(room.join(), Some(0));
+    assert!(room.is_waiting());
+    for player in 1..MAX_ROOM_PLAYERS {
+        assert_eq!(room.join(), Some(player));
+    }
+    assert_eq!(room.join(), None);
+    assert!(!room.is_waiting());
+
+    let x = room.heroes()[1].x;
+    for seq in 1..=30 {
+        room.input(1, seq, walking_right());
+        room.step();
+    }
+    // a repeated input is not played twice
+    room.input(1, 30, walking_right());
+    room.step();
+    assert_eq!(room.state(1).ack, 30);
+    // the last input goes on until another one comes
+    assert_eq!(room.heroes()[1].x, x + 2.0 * 31.0);
+    assert_eq!(room.state(0).ack, 0);
+
+    room.leave(2);
+    assert_eq!(room.players(), 3);
+    assert!(room.heroes().iter().all(|h| h.player != 2));
+    assert_eq!(room.join(), Some(2));
+    assert_eq!(room.heroes()[2].player, 2);
+}
+
+#[test]
+fn the_own_hero_is_predicted_from_unplayed_inputs() {
+    let mut room = Room::new();
+    room.join();
+    room.join();
+    let mut session = Session::new(1);
+    let x = room.heroes()[1].x;
+    for _ in 0..10 {
+        if let ClientMessage::Input { seq, input } = session.input(walking_right()) {
+            room.input(1, seq, input);
+        }
+    }
+    // the server played 4 of the 10 inputs so far
+    for _ in 0..4 {
+        room.step();
+    }
+    session.receive(room.state(1));
+    assert_eq!(session.pending(), 6);
+    let (heroes, _) = session.frame().unwrap();
+    assert_eq!(heroes[0].player, 1);
+    assert_eq!(heroes[0].x, x + 2.0 * 10.0);
+}
+
+#[test]
+fn states_carry_the_events_since_the_last_ones() {
+    let mut room = Room::new();
+    room.join();
+    room.join();
+    // the horde comes into the world
+    for _ in 0..600 {
+        room.step();
+        if !room.state(0).events.is_empty() {
+            break;
+        }
+    }
+    let events = room.state(0).events;
+    assert!(matches!(events[0], Event::ZombieEntered { .. }));
+    assert_eq!(room.state(1).events, events);
+
+    let mut session = Session::new(1);
+    session.receive(room.state(1));
+    assert_eq!(session.take_events(), events);
+    assert!(session.take_events().is_empty());
+    room.states_sent();
+    assert!(room.state(0).events.is_empty());
+}
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+fn connect(addr: &str, room: &str) -> (Socket, usize) {
+    let join = ClientMessage::Join {
+        room: room.to_string(),
+    };
+    let (socket, player) = join_with(addr, &join);
+    (socket, player.unwrap())
+}
+
+// The player the server seated the client as, None if it was refused.
+fn join_with(addr: &str, join: &ClientMessage) -> (Socket, Option<usize>) {
+    let (mut socket, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+    socket.send(Message::Text(join.to_json())).unwrap();
+    loop {
+        match receive(&mut socket) {
+            ServerMessage::Welcome { player } => return (socket, Some(player)),
+            ServerMessage::Refused { .. } => return (socket, None),
+            _ => {}
+        }
+    }
+}
+
+fn receive(socket: &mut Socket) -> ServerMessage {
+    loop {
+        if let Message::Text(text) = socket.read().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+// Reads states until one passes the test, failing after a few seconds.
+fn wait_for(socket: &mut Socket, test: impl Fn(&State) -> bool) -> State {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if let ServerMessage::State(state) = receive(socket) {
+            if test(&state) {
+                return *state;
+            }
+        }
+    }
+    panic!("no such state came");
+}
+
+fn start_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || serve(listener));
+    addr
+}
+
+#[test]
+fn headless_clients_play_together_on_localhost() {
+    let addr = start_server();
+
+    let (mut first, one) = connect(&addr, "test");
+    let (mut second, two) = connect(&addr, "test");
+    assert_eq!((one, two), (0, 1));
+    let (mut elsewhere, alone) = connect(&addr, "other");
+    assert_eq!(alone, 0);
+    let state = wait_for(&mut elsewhere, |_| true);
+    assert!(state.waiting);
+
+    let start = wait_for(&mut first, |s| !s.waiting);
+    let x = start.heroes[0].x;
+    let mut session = Session::new(one);
+    // as many as the room queues, more at once would be dropped
+    for _ in 0..10 {
+        let message = session.input(walking_right());
+        first.send(Message::Text(message.to_json())).unwrap();
+    }
+    let state = wait_for(&mut first, |s| s.ack == 10);
+    assert_eq!(state.heroes.len(), 2);
+    assert!(state.heroes[0].x >= x + 2.0 * 10.0);
+    // the other player sees the hero walk too
+    let seen = wait_for(&mut second, |s| s.tick >= state.tick);
+    assert!(seen.heroes[0].x >= x + 2.0 * 10.0);
+
+    // the seat of a player who left is free again
+    first.close(None).unwrap();
+    drop(first);
+    thread::sleep(Duration::from_millis(100));
+    let (_third, player) = connect(&addr, "test");
+    assert_eq!(player, 0);
+}
+
+#[test]
+fn rollback_rooms_only_pass_the_inputs_around() {
+    let addr = start_server();
+    let join = ClientMessage::JoinRollback {
+        room: "peers".to_string(),
+        players: 2,
+    };
+    let (mut first, one) = join_with(&addr, &join);
+    let (mut second, two) = join_with(&addr, &join);
+    assert_eq!((one, two), (Some(0), Some(1)));
+    let (_late, refused) = join_with(&addr, &join);
+    assert_eq!(refused, None);
+
+    let start = |socket: &mut Socket| loop {
+        if let ServerMessage::Start { seed, players } = receive(socket) {
+            return (seed, players);
+        }
+    };
+    let (seed, players) = start(&mut first);
+    assert_eq!(start(&mut second), (seed, players));
+    assert_eq!(players, 2);
+
+    let mut rollback = Rollback::new(seed, players, 0).unwrap();
+    for _ in 0..5 {
+        rollback.advance(walking_right());
+    }
+    // one ack short of the players, dropped
+    let mut short = rollback.message();
+    short.acks.pop();
+    short.inputs.truncate(3);
+    first
+        .send(Message::Text(ClientMessage::Inputs(short).to_json()))
+        .unwrap();
+    let mut message = rollback.message();
+    // nobody speaks for another hero
+    message.player = 1;
+    let inputs = ClientMessage::Inputs(message);
+    first.send(Message::Text(inputs.to_json())).unwrap();
+    loop {
+        if let ServerMessage::Inputs(inputs) = receive(&mut second) {
+            assert_eq!(inputs.player, 0);
+            assert_eq!(inputs.inputs.len(), 5);
+            break;
+        }
+    }
+
+    drop(first);
+    loop {
+        if let ServerMessage::Left { player } = receive(&mut second) {
+            assert_eq!(player, 0);
+            break;
+        }
+    }
+}
+
+#[test]
+fn rollback_rooms_refuse_seated_clients_and_other_sizes() {
+    let addr = start_server();
+    let join = |players| ClientMessage::JoinRollback {
+        room: "strict".to_string(),
+        players,
+    };
+    let (mut first, one) = join_with(&addr, &join(3));
+    assert_eq!(one, Some(0));
+    // the first picked three players
+    assert_eq!(join_with(&addr, &join(2)).1, None);
+    assert_eq!(join_with(&addr, &join(MAX_ROOM_PLAYERS + 1)).1, None);
+    let (mut second, two) = join_with(&addr, &join(3));
+    assert_eq!(two, Some(1));
+
+    // joining again keeps the seat and tells nobody it left
+    second.send(Message::Text(join(3).to_json())).unwrap();
+    assert!(matches!(
+        receive(&mut second),
+        ServerMessage::Refused { .. }
+    ));
+    let (_third, three) = join_with(&addr, &join(3));
+    assert_eq!(three, Some(2));
+    loop {
+        match receive(&mut first) {
+            ServerMessage::Start { players, .. } => {
+                assert_eq!(players, 3);
+                break;
+            }
+            ServerMessage::Left { .. } => panic!("nobody left"),
+            _ => {}
+        }
+    }
+}
+
+// A run of ticks ticks, the hero walking right.
+fn walked(seed: u64, ticks: u64) -> Replay {
+    let mut replay = Replay::new(seed, 1);
+    for _ in 0..ticks {
+        replay.push(walking_right());
+    }
+    replay
+}
+
+#[test]
+fn stages_keep_the_run_for_late_watchers() {
+    let mut stage = Stage::default();
+    assert!(stage.watch(1));
+    assert!(stage.broadcast(0));
+    assert!(!stage.broadcast(2));
+    assert!(stage.run().is_none());
+
+    let mut broadcast = Broadcast::default();
+    let run = walked(4, 30);
+    let first = broadcast.ticks(&run).unwrap();
+    // only the broadcaster is heard
+    assert!(!stage.receive(1, &first));
+    assert!(stage.receive(0, &first));
+    let mut longer = run.clone();
+    longer.append(&walked(4, 20));
+    let next = broadcast.ticks(&longer).unwrap();
+    assert_eq!(next.first, 30);
+    // a gap is not taken
+    assert!(!stage.receive(
+        0,
+        &Ticks {
+            first: 31,
+            ..next.clone()
+        }
+    ));
+    assert!(stage.receive(0, &next));
+    assert_eq!(
+        stage.run(),
+        Some(Ticks {
+            first: 0,
+            replay: longer
+        })
+    );
+
+    for watcher in 2..=MAX_WATCHERS as u64 {
+        assert!(stage.watch(watcher));
+    }
+    assert!(!stage.watch(100));
+    assert!(!stage.leave(1));
+    assert!(stage.leave(0));
+    assert!(!stage.is_empty());
+    for watcher in stage.watchers().collect::<Vec<_>>() {
+        stage.leave(watcher);
+    }
+    assert!(stage.is_empty());
+}
+
+#[test]
+fn watchers_get_the_broadcast_run_on_localhost() {
+    let addr = start_server();
+    let connect_as = |message: ClientMessage| {
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+        socket.send(Message::Text(message.to_json())).unwrap();
+        socket
+    };
+    let mut broadcaster = connect_as(ClientMessage::Broadcast {
+        room: "show".to_string(),
+    });
+    let mut broadcast = Broadcast::default();
+    let send = |socket: &mut Socket, broadcast: &mut Broadcast, run: &Replay| {
+        let ticks = ClientMessage::Ticks(broadcast.ticks(run).unwrap());
+        socket.send(Message::Text(ticks.to_json())).unwrap();
+    };
+    let mut run = walked(6, 40);
+    send(&mut broadcaster, &mut broadcast, &run);
+
+    // the second broadcaster is refused, the watcher gets the run so far
+    let mut other = connect_as(ClientMessage::Broadcast {
+        room: "show".to_string(),
+    });
+    assert!(matches!(receive(&mut other), ServerMessage::Refused { .. }));
+    let mut watcher = connect_as(ClientMessage::Watch {
+        room: "show".to_string(),
+    });
+    let mut spectator = Spectator::default();
+    let next_ticks = |socket: &mut Socket| loop {
+        if let ServerMessage::Ticks(ticks) = receive(socket) {
+            return ticks;
+        }
+    };
+    let so_far = next_ticks(&mut watcher);
+    assert_eq!(so_far.first, 0);
+    assert_eq!(so_far.replay.ticks(), 40);
+    spectator.receive(&so_far);
+
+    // then the ticks as they are played
+    run.append(&walked(6, 10));
+    send(&mut broadcaster, &mut broadcast, &run);
+    let later = next_ticks(&mut watcher);
+    assert_eq!(later.first, 40);
+    spectator.receive(&later);
+    assert_eq!(spectator.behind(), 50);
+    spectator.advance(0.0);
+    let x = spectator.frame().unwrap().heroes[0].x;
+    while spectator.behind() > 0 {
+        spectator.advance(TICK);
+    }
+    assert_eq!(spectator.frame().unwrap().heroes[0].x, x + 2.0 * 30.0);
+
+    drop(broadcaster);
+    assert!(matches!(receive(&mut watcher), ServerMessage::Stopped));
+}
+
+#[test]
+fn seated_clients_are_refused_before_the_stage_changes() {
+    let addr = start_server();
+    let connect_as = |message: ClientMessage| {
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+        socket.send(Message::Text(message.to_json())).unwrap();
+        socket
+    };
+    let room = || "seats".to_string();
+    let mut broadcaster = connect_as(ClientMessage::Broadcast { room: room() });
+    let mut broadcast = Broadcast::default();
+    let run = walked(9, 20);
+    let ticks = ClientMessage::Ticks(broadcast.ticks(&run).unwrap());
+    broadcaster.send(Message::Text(ticks.to_json())).unwrap();
+    let mut watcher = connect_as(ClientMessage::Watch { room: room() });
+    assert!(matches!(receive(&mut watcher), ServerMessage::Ticks(_)));
+
+    // neither watching its own room nor broadcasting in another one
+    for again in [
+        ClientMessage::Watch { room: room() },
+        ClientMessage::Broadcast {
+            room: "elsewhere".to_string(),
+        },
+    ] {
+        broadcaster.send(Message::Text(again.to_json())).unwrap();
+        assert!(matches!(
+            receive(&mut broadcaster),
+            ServerMessage::Refused { .. }
+        ));
+    }
+    watcher
+        .send(Message::Text(
+            ClientMessage::Watch { room: room() }.to_json(),
+        ))
+        .unwrap();
+    assert!(matches!(
+        receive(&mut watcher),
+        ServerMessage::Refused { .. }
+    ));
+
+    // still the broadcaster, and the watcher was not told otherwise
+    let mut longer = run.clone();
+    longer.append(&walked(9, 5));
+    let ticks = ClientMessage::Ticks(broadcast.ticks(&longer).unwrap());
+    broadcaster.send(Message::Text(ticks.to_json())).unwrap();
+    match receive(&mut watcher) {
+        ServerMessage::Ticks(ticks) => assert_eq!(ticks.first, 20),
+        _ => panic!("the watcher lost the broadcast"),
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Event;
+use crate::stats::Stats;
+use crate::storage;
+
+const STORAGE_KEY: &str = "herpooles.achievements";
+// Bumped when the stored layout changes, unlocks stored by another version are dropped.
+const SCHEMA_VERSION: u32 = 1;
+
+// What an achievement asks for. Progress towards a goal is a count up to its target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Goal {
+    // get through the level throwing at most this many poo on it
+    ClearLevelWithin { level: u16, shots: u32 },
+    // kill this many zombies within this many simulation seconds
+    KillStreak { kills: u32, seconds: f64 },
+    // get through any level without walking into the edge of the world
+    ClearLevelOffEdge,
+    ReachLevel(u16),
+}
+
+impl Goal {
+    pub fn target(&self) -> u32 {
+        match *self {
+            Goal::KillStreak { kills, .. } => kills,
+            Goal::ReachLevel(level) => level.into(),
+            Goal::ClearLevelWithin { .. } | Goal::ClearLevelOffEdge => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Achievement {
+    // the key of its unlock state, must not change
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub goal: Goal,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "sharpshooter",
+        title: "Sharpshooter",
+        description: "clear level 5 throwing at most 60 poo",
+        goal: Goal::ClearLevelWithin {
+            level: 5,
+            shots: 60,
+        },
+    },
+    Achievement {
+        id: "frenzy",
+        title: "Frenzy",
+        description: "kill 10 zombies within 2 seconds",
+        goal: Goal::KillStreak {
+            kills: 10,
+            seconds: 2.0,
+        },
+    },
+    Achievement {
+        id: "center-stage",
+        title: "Center stage",
+        description: "clear a level without touching the edge",
+        goal: Goal::ClearLevelOffEdge,
+    },
+    Achievement {
+        id: "veteran",
+        title: "Veteran",
+        description: "reach level 10",
+        goal: Goal::ReachLevel(10),
+    },
+];
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    version: u32,
+    // when each achievement was unlocked, milliseconds since the epoch as Date.now()
+    unlocked: BTreeMap<String, f64>,
+    // the best progress so far of the others
+    progress: BTreeMap<String, u32>,
+}
+
+// The unlock state of every achievement, kept across runs, and what the current run has
+// done towards them.
+#[derive(Clone, Debug, Default)]
+pub struct Achievements {
+    unlocked: BTreeMap<String, f64>,
+    progress: BTreeMap<String, u32>,
+    // simulation times of the recent kills
+    kills: VecDeque<f64>,
+    // not saved yet
+    changed: bool,
+}
+
+impl Achievements {
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains_key(id)
+    }
+
+    pub fn unlocked_count(&self) -> usize {
+        self.unlocked.len()
+    }
+
+    // The best progress towards an achievement, its target once unlocked.
+    pub fn progress(&self, achievement: &Achievement) -> u32 {
+        if self.is_unlocked(achievement.id) {
+            achievement.goal.target()
+        } else {
+            self.progress.get(achievement.id).copied().unwrap_or(0)
+        }
+    }
+
+    // Kills within the last seconds.
+    fn streak(&self, now: f64, seconds: f64) -> u32 {
+        self.kills.iter().filter(|&&t| now - t < seconds).count() as u32
+    }
+
+    fn reached(&self, goal: Goal, stats: &Stats, now: f64) -> u32 {
+        match goal {
+            Goal::ClearLevelWithin { level, shots } => stats
+                .cleared()
+                .iter()
+                .any(|l| l.level == level && l.shots <= shots)
+                .into(),
+            Goal::KillStreak { seconds, .. } => self.streak(now, seconds),
+            Goal::ClearLevelOffEdge => stats.cleared().iter().any(|l| l.edge_touches == 0).into(),
+            Goal::ReachLevel(_) => stats.levels.last().map_or(0, |l| l.level.into()),
+        }
+    }
+
+    // Follows the run after a tick, with the events of the tick and the statistics so far.
+    // now is the simulation time, date the time of day in milliseconds since the epoch.
+    // Returns the achievements unlocked by it.
+    pub fn update(
+        &mut self,
+        events: &[Event],
+        stats: &Stats,
+        now: f64,
+        date: f64,
+    ) -> Vec<&'static Achievement> {
+        let window = ACHIEVEMENTS
+            .iter()
+            .filter_map(|a| match a.goal {
+                Goal::KillStreak { seconds, .. } => Some(seconds),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        // kills from another run are in the future
+        self.kills.retain(|&t| t <= now && now - t < window);
+        for event in events {
+            if let Event::ZombieKilled { .. } = event {
+                self.kills.push_back(now);
+            }
+        }
+
+        let mut unlocked = vec![];
+        for achievement in ACHIEVEMENTS {
+            if self.is_unlocked(achievement.id) {
+                continue;
+            }
+            let target = achievement.goal.target();
+            let reached = self.reached(achievement.goal, stats, now).min(target);
+            if reached >= target {
+                self.unlocked.insert(achievement.id.to_string(), date);
+                self.progress.remove(achievement.id);
+                self.changed = true;
+                unlocked.push(achievement);
+            } else if reached > self.progress(achievement) {
+                self.progress.insert(achievement.id.to_string(), reached);
+                self.changed = true;
+            }
+        }
+        unlocked
+    }
+
+    fn stored(&self) -> Stored {
+        Stored {
+            version: SCHEMA_VERSION,
+            unlocked: self.unlocked.clone(),
+            progress: self.progress.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.stored()).expect("serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Achievements, String> {
+        let stored: Stored = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if stored.version != SCHEMA_VERSION {
+            return Err(format!("unknown achievements version {}", stored.version));
+        }
+        Ok(Achievements {
+            unlocked: stored.unlocked,
+            progress: stored.progress,
+            ..Achievements::default()
+        })
+    }
+
+    pub fn load() -> Achievements {
+        let Some(stored) = storage::load::<serde_json::Value>(STORAGE_KEY) else {
+            return Achievements::default();
+        };
+        Achievements::from_json(&stored.to_string()).unwrap_or_else(|e| {
+            log!("ignoring stored achievements: {}", e);
+            Achievements::default()
+        })
+    }
+
+    // Stores the unlocks and the progress if anything changed since the last time.
+    pub fn save_if_changed(&mut self) {
+        if self.changed {
+            storage::save(STORAGE_KEY, &self.stored());
+            self.changed = false;
+        }
+    }
+}
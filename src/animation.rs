@@ -0,0 +1,100 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+// All times are simulation seconds, see game::Controller::time.
+
+// How long a dying character takes to fall over before it is removed.
+pub const DEATH_DURATION: f64 = 0.6;
+// Distance walked for one full leg cycle.
+const STRIDE: f32 = 24.0;
+// Blinks per second while flashing.
+const FLASH_RATE: f64 = 10.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum State {
+    Walking,
+    Dying { since: f64 },
+    Done,
+}
+
+// The animation state machine of a character: Walking -> Dying -> Done.
+// The simulation drives the transitions, the renderer only reads it.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Animation {
+    pub state: State,
+    // strides walked, the fractional part is the position in the walk cycle
+    walk_phase: f32,
+    flash_until: f64,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            state: State::Walking,
+            walk_phase: 0.0,
+            // long ago, finite so it survives JSON
+            flash_until: f64::MIN,
+        }
+    }
+}
+
+impl Animation {
+    // Advances the walk cycle by the distance moved, so the legs keep up with the speed.
+    pub fn walk(&mut self, distance: f32) {
+        if self.state == State::Walking {
+            self.walk_phase = (self.walk_phase + distance / STRIDE).fract();
+        }
+    }
+
+    pub fn die(&mut self, now: f64) {
+        if self.state == State::Walking {
+            self.state = State::Dying { since: now };
+        }
+    }
+
+    pub fn flash(&mut self, now: f64, duration: f64) {
+        self.flash_until = now + duration;
+    }
+
+    pub fn update(&mut self, now: f64) {
+        if let State::Dying { since } = self.state {
+            if now - since >= DEATH_DURATION {
+                self.state = State::Done;
+            }
+        }
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.state == State::Walking
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // Leg offset in [-1, 1] along the walk cycle.
+    pub fn leg_swing(&self) -> f32 {
+        (self.walk_phase * 2.0 * PI).sin()
+    }
+
+    // Which of frames evenly spaced poses of the walk cycle the legs are closest to
+    // without going past.
+    pub fn walk_frame(&self, frames: usize) -> usize {
+        ((self.walk_phase * frames as f32) as usize).min(frames - 1)
+    }
+
+    // From 0 when the character starts dying to 1 when it is gone.
+    pub fn death_progress(&self, now: f64) -> f32 {
+        match self.state {
+            State::Walking => 0.0,
+            State::Dying { since } => ((now - since) / DEATH_DURATION).clamp(0.0, 1.0) as f32,
+            State::Done => 1.0,
+        }
+    }
+
+    // Whether to draw the flash colour this frame.
+    pub fn flashing(&self, now: f64) -> bool {
+        now < self.flash_until && (((self.flash_until - now) * FLASH_RATE) as u64).is_multiple_of(2)
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::audio::{Sound, SoundManager};
+use crate::render;
+use crate::theme::Theme;
+use crate::window;
+
+// Every image the game draws comes from the atlas of the manifest, every sound it plays is
+// listed there by name.
+const MANIFEST_URL: &str = "resources/manifest.json";
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub struct Frame {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    image: String,
+    frames: HashMap<String, Frame>,
+    sounds: HashMap<Sound, String>,
+}
+
+// A single image holding all the sprites, and the named rectangles inside it.
+pub struct Atlas {
+    image: web_sys::HtmlImageElement,
+    frames: HashMap<String, Frame>,
+}
+
+impl Atlas {
+    // Draws the named frame with its top left corner at x, y.
+    pub fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d, name: &str, x: f64, y: f64) {
+        let Some(f) = self.frames.get(name) else {
+            log!("no frame {} in the atlas", name);
+            return;
+        };
+        ctx.draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+            &self.image,
+            f.x,
+            f.y,
+            f.w,
+            f.h,
+            x,
+            y,
+            f.w,
+            f.h,
+        )
+        .unwrap();
+    }
+}
+
+pub struct Assets {
+    pub atlas: Atlas,
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    // urls that could not be fetched or decoded
+    Missing(Vec<String>),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Missing(urls) => write!(f, "missing assets: {}", urls.join(", ")),
+        }
+    }
+}
+
+impl From<AssetError> for JsValue {
+    fn from(e: AssetError) -> JsValue {
+        JsValue::from_str(&e.to_string())
+    }
+}
+
+pub async fn fetch(url: &str) -> Result<web_sys::Response, JsValue> {
+    let response: web_sys::Response = JsFuture::from(window().fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!("status {}", response.status())));
+    }
+    Ok(response)
+}
+
+async fn load_manifest() -> Result<Manifest, JsValue> {
+    let text = JsFuture::from(fetch(MANIFEST_URL).await?.text()?).await?;
+    let text = text.as_string().unwrap_or_default();
+    serde_json::from_str(&text).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// A theme from a JSON file, see theme::Theme.
+pub async fn load_theme(url: &str) -> Result<Theme, JsValue> {
+    let text = JsFuture::from(fetch(url).await?.text()?).await?;
+    let text = text.as_string().unwrap_or_default();
+    Theme::from_json(&text).map_err(|e| JsValue::from_str(&e))
+}
+
+async fn load_image(url: &str) -> Result<web_sys::HtmlImageElement, JsValue> {
+    let image = web_sys::HtmlImageElement::new()?;
+    image.set_src(url);
+    // decode() rejects when the image cannot be loaded
+    JsFuture::from(image.decode()).await?;
+    Ok(image)
+}
+
+// Loads everything the game needs before the first frame, drawing a progress bar on the
+// canvas as it goes. All failures are collected so a broken deploy lists every missing file.
+pub async fn load(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    sounds: &SoundManager,
+) -> Result<Assets, AssetError> {
+    let total = 2 + Sound::ALL.len();
+    let mut loaded = 0;
+    let mut missing = vec![];
+    render::draw_loading(ctx, loaded, total);
+
+    let manifest = match load_manifest().await {
+        Ok(file) => Some(file),
+        Err(e) => {
+            log!("could not load {}: {:?}", MANIFEST_URL, e);
+            missing.push(MANIFEST_URL.to_string());
+            None
+        }
+    };
+    loaded += 1;
+    render::draw_loading(ctx, loaded, total);
+
+    let mut atlas = None;
+    let listed = manifest.is_some();
+    let mut sound_urls = HashMap::new();
+    if let Some(file) = manifest {
+        sound_urls = file.sounds;
+        match load_image(&file.image).await {
+            Ok(image) => {
+                atlas = Some(Atlas {
+                    image,
+                    frames: file.frames,
+                })
+            }
+            Err(e) => {
+                log!("could not load {}: {:?}", file.image, e);
+                missing.push(file.image);
+            }
+        }
+    }
+    loaded += 1;
+    render::draw_loading(ctx, loaded, total);
+
+    for sound in Sound::ALL {
+        match sound_urls.get(&sound) {
+            Some(url) => {
+                if let Err(e) = sounds.load(sound, url).await {
+                    log!("could not load {}: {:?}", url, e);
+                    missing.push(url.clone());
+                }
+            }
+            // without a manifest it is the only one missing
+            None if !listed => {}
+            None => missing.push(format!("{:?} in {}", sound, MANIFEST_URL)),
+        }
+        loaded += 1;
+        render::draw_loading(ctx, loaded, total);
+    }
+
+    match atlas {
+        Some(atlas) if missing.is_empty() => Ok(Assets { atlas }),
+        _ => Err(AssetError::Missing(missing)),
+    }
+}
@@ -0,0 +1,280 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::assets;
+use crate::camera::Camera;
+use crate::game::Event;
+use crate::geometry::{Point, Vector};
+use crate::settings::Settings;
+
+// More overlapping voices than this are dropped instead of cutting off the ones playing.
+const MAX_VOICES: u32 = 8;
+// Distance from the listener at which a sound is played at half volume.
+const HALF_VOLUME_DISTANCE: f32 = 400.0;
+// Far away sounds are still heard a bit, to tell that something happened.
+const MIN_GAIN: f32 = 0.15;
+
+// Stereo pan in [-1, 1] from the horizontal position on screen, and a gain that falls
+// off with the distance from the listener.
+pub fn spatialize(pos: Point, listener: Point, view: &Camera) -> (f32, f32) {
+    let pan = ((pos.x - view.x) / view.width * 2.0 - 1.0).clamp(-1.0, 1.0);
+    let v = Vector::new(listener, pos);
+    let distance = (v.x * v.x + v.y * v.y).sqrt();
+    let gain = HALF_VOLUME_DISTANCE / (HALF_VOLUME_DISTANCE + distance);
+    (pan, gain.max(MIN_GAIN))
+}
+
+// Named as in the sounds of the asset manifest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sound {
+    ZombieDie,
+    ZombieHit,
+    ZombieGroan,
+    Music,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 4] = [
+        Sound::ZombieDie,
+        Sound::ZombieHit,
+        Sound::ZombieGroan,
+        Sound::Music,
+    ];
+
+    // Seconds before the same sound can play again. A horde walking in would otherwise
+    // groan on every frame.
+    fn min_interval(&self) -> f64 {
+        match self {
+            Sound::ZombieGroan => 0.4,
+            _ => 0.0,
+        }
+    }
+}
+
+// The audio graph: music and effects go through their own gain into the master gain,
+// so each volume setting is a single gain value.
+struct Output {
+    ctx: web_sys::AudioContext,
+    master: web_sys::GainNode,
+    music: web_sys::GainNode,
+    effects: web_sys::GainNode,
+}
+
+impl Output {
+    fn new() -> Result<Output, JsValue> {
+        let ctx = web_sys::AudioContext::new()?;
+        let master = ctx.create_gain()?;
+        master.connect_with_audio_node(&ctx.destination())?;
+        let music = ctx.create_gain()?;
+        music.connect_with_audio_node(&master)?;
+        let effects = ctx.create_gain()?;
+        effects.connect_with_audio_node(&master)?;
+        Ok(Output {
+            ctx,
+            master,
+            music,
+            effects,
+        })
+    }
+
+    fn start(
+        &self,
+        buffer: &web_sys::AudioBuffer,
+        bus: &web_sys::AudioNode,
+        looping: bool,
+    ) -> Result<web_sys::AudioBufferSourceNode, JsValue> {
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(buffer));
+        source.set_loop(looping);
+        source.connect_with_audio_node(bus)?;
+        source.start()?;
+        Ok(source)
+    }
+
+    // source -> gain -> panner -> effects
+    fn start_spatial(
+        &self,
+        buffer: &web_sys::AudioBuffer,
+        pan: f32,
+        gain: f32,
+    ) -> Result<web_sys::AudioBufferSourceNode, JsValue> {
+        let panner = self.ctx.create_stereo_panner()?;
+        panner.pan().set_value(pan);
+        panner.connect_with_audio_node(&self.effects)?;
+        let volume = self.ctx.create_gain()?;
+        volume.gain().set_value(gain);
+        volume.connect_with_audio_node(&panner)?;
+        self.start(buffer, &volume, false)
+    }
+}
+
+// Clips are decoded once into buffers and every play gets its own buffer source node,
+// so sounds overlap instead of restarting a single <audio> element.
+pub struct SoundManager {
+    // None when the browser has no Web Audio, every play is then a no-op.
+    out: Option<Output>,
+    buffers: RefCell<HashMap<Sound, web_sys::AudioBuffer>>,
+    voices: Rc<Cell<u32>>,
+    // AudioContext time each sound was last played at
+    last_played: RefCell<HashMap<Sound, f64>>,
+    music_playing: Cell<bool>,
+}
+
+impl SoundManager {
+    pub fn new(settings: &Settings) -> Rc<SoundManager> {
+        let out = match Output::new() {
+            Ok(out) => Some(out),
+            Err(e) => {
+                log!("audio unavailable: {:?}", e);
+                None
+            }
+        };
+        let manager = Rc::new(SoundManager {
+            out,
+            buffers: RefCell::new(HashMap::new()),
+            voices: Rc::new(Cell::new(0)),
+            last_played: RefCell::new(HashMap::new()),
+            music_playing: Cell::new(false),
+        });
+        manager.apply(settings);
+        manager
+    }
+
+    // Fetches and decodes a clip. Without Web Audio there is nothing to decode into and
+    // the clip is skipped. The music starts as soon as it is decoded.
+    pub async fn load(&self, sound: Sound, url: &str) -> Result<(), JsValue> {
+        let Some(out) = &self.out else {
+            return Ok(());
+        };
+        let buffer = decode(&out.ctx, url).await?;
+        self.buffers.borrow_mut().insert(sound, buffer);
+        if sound == Sound::Music {
+            self.play_music();
+        }
+        Ok(())
+    }
+
+    // Browsers keep a new AudioContext suspended until the page gets a user gesture.
+    pub fn unlock_on_gesture(self: &Rc<Self>, document: &web_sys::Document) {
+        let Some(out) = &self.out else {
+            return;
+        };
+        let ctx = out.ctx.clone();
+        let unlock_closure = Closure::wrap(Box::new(move || {
+            if ctx.state() == web_sys::AudioContextState::Suspended {
+                if let Ok(promise) = ctx.resume() {
+                    spawn_local(async move {
+                        if let Err(e) = JsFuture::from(promise).await {
+                            log!("could not resume audio: {:?}", e);
+                        }
+                    });
+                }
+            }
+        }) as Box<dyn Fn()>);
+        for event in ["keydown", "mousedown", "touchstart"] {
+            document
+                .add_event_listener_with_callback(event, unlock_closure.as_ref().unchecked_ref())
+                .unwrap();
+        }
+        unlock_closure.forget();
+    }
+
+    pub fn apply(&self, settings: &Settings) {
+        let Some(out) = &self.out else {
+            return;
+        };
+        let master = if settings.muted { 0.0 } else { settings.master };
+        out.master.gain().set_value(master);
+        out.music.gain().set_value(settings.music);
+        out.effects.gain().set_value(settings.effects);
+    }
+
+    pub fn play(&self, sound: Sound) {
+        self.play_voice(sound, 0.0, 1.0);
+    }
+
+    // Plays a sound effect that happened at pos, heard from the listener's position.
+    pub fn play_at(&self, sound: Sound, pos: Point, listener: Point, view: &Camera) {
+        let (pan, gain) = spatialize(pos, listener, view);
+        self.play_voice(sound, pan, gain);
+    }
+
+    // The sound effect of a game event, heard from herpooles' position.
+    pub fn play_event(&self, event: &Event, listener: Point, view: &Camera) {
+        match *event {
+            Event::ZombieKilled { x, y } => {
+                self.play_at(Sound::ZombieDie, Point { x, y }, listener, view)
+            }
+            Event::HerpoolesDied { x, y } => {
+                self.play_at(Sound::ZombieHit, Point { x, y }, listener, view)
+            }
+            Event::ZombieEntered { x, y } => {
+                self.play_at(Sound::ZombieGroan, Point { x, y }, listener, view)
+            }
+            Event::PooSplat { .. } => {}
+        }
+    }
+
+    fn play_voice(&self, sound: Sound, pan: f32, gain: f32) {
+        let Some(out) = &self.out else {
+            return;
+        };
+        if self.voices.get() >= MAX_VOICES {
+            return;
+        }
+        let now = out.ctx.current_time();
+        if let Some(last) = self.last_played.borrow().get(&sound) {
+            if now - last < sound.min_interval() {
+                return;
+            }
+        }
+        let buffers = self.buffers.borrow();
+        let Some(buffer) = buffers.get(&sound) else {
+            return;
+        };
+        match out.start_spatial(buffer, pan, gain) {
+            Ok(source) => {
+                // the voice is freed when the clip ends
+                let voices = self.voices.clone();
+                let ended =
+                    Closure::once_into_js(move || voices.set(voices.get().saturating_sub(1)));
+                let scheduled: &web_sys::AudioScheduledSourceNode = source.as_ref();
+                scheduled.set_onended(Some(ended.unchecked_ref()));
+                self.voices.set(self.voices.get() + 1);
+                self.last_played.borrow_mut().insert(sound, now);
+            }
+            Err(e) => log!("could not play {:?}: {:?}", sound, e),
+        }
+    }
+
+    fn play_music(&self) {
+        let Some(out) = &self.out else {
+            return;
+        };
+        if self.music_playing.get() {
+            return;
+        }
+        let buffers = self.buffers.borrow();
+        let Some(buffer) = buffers.get(&Sound::Music) else {
+            return;
+        };
+        match out.start(buffer, &out.music, true) {
+            Ok(_) => self.music_playing.set(true),
+            Err(e) => log!("could not play music: {:?}", e),
+        }
+    }
+}
+
+async fn decode(ctx: &web_sys::AudioContext, url: &str) -> Result<web_sys::AudioBuffer, JsValue> {
+    let response = assets::fetch(url).await?;
+    let data: js_sys::ArrayBuffer = JsFuture::from(response.array_buffer()?).await?.dyn_into()?;
+    JsFuture::from(ctx.decode_audio_data(&data)?)
+        .await?
+        .dyn_into()
+}
@@ -1,66 +1,91 @@
-use crate::game::Herpooles;
-use crate::PressedKeys;
-use crate::{cancel_animation_frame, request_animation_frame};
+use crate::audio::SoundManager;
+use crate::figures::Skin;
+use crate::game::{Controller, Herpooles};
+use crate::hud::Hud;
+use crate::online;
+use crate::scores::{Entry, HighScores};
+use crate::screen::{self, Action, Screen, Transition, FIRE_KEYS};
+use crate::settings::Settings;
+use crate::snapshot::Snapshot;
+use crate::theme::Theme;
+use crate::viewport;
+use crate::window;
+use crate::{KeySets, PressedKeys};
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
 // TODO: Deprecated, use codes
-enum KeyboardCodes {
+pub(crate) enum KeyboardCodes {
     Left = 37,
     Up = 38,
     Right = 39,
     Down = 40,
     Spacebar = 32,
+    Escape = 27,
+    P = 80,
+    R = 82,
+    Enter = 13,
+    M = 77,
+    Q = 81,
+    C = 67,
+    W = 87,
+    A = 65,
+    S = 83,
+    D = 68,
+    Shift = 16,
+    Two = 50,
+    V = 86,
 }
 
-//TODO: use RefCell and borrow?
-fn handle_keydown_event(event: web_sys::KeyboardEvent, pressed: &Rc<Cell<PressedKeys>>) {
-    if event.key_code() == KeyboardCodes::Left as u32 {
-        let mut keys = pressed.take();
-        keys.left = true;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Right as u32 {
-        let mut keys = pressed.take();
-        keys.right = true;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Up as u32 {
-        let mut keys = pressed.take();
-        keys.up = true;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Down as u32 {
-        let mut keys = pressed.take();
-        keys.down = true;
-        pressed.set(keys);
+// The set of held keys a movement key belongs to, WASD the first and the arrows the
+// second, and which of them it is.
+type MovementKey = (usize, fn(&mut PressedKeys) -> &mut bool);
+
+fn movement_key(code: u32) -> Option<MovementKey> {
+    let key: MovementKey = match code {
+        c if c == KeyboardCodes::A as u32 => (0, |k| &mut k.left),
+        c if c == KeyboardCodes::D as u32 => (0, |k| &mut k.right),
+        c if c == KeyboardCodes::W as u32 => (0, |k| &mut k.up),
+        c if c == KeyboardCodes::S as u32 => (0, |k| &mut k.down),
+        c if c == KeyboardCodes::Left as u32 => (1, |k| &mut k.left),
+        c if c == KeyboardCodes::Right as u32 => (1, |k| &mut k.right),
+        c if c == KeyboardCodes::Up as u32 => (1, |k| &mut k.up),
+        c if c == KeyboardCodes::Down as u32 => (1, |k| &mut k.down),
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn handle_movement_key(event: &web_sys::KeyboardEvent, pressed: &Rc<Cell<KeySets>>, down: bool) {
+    if let Some((set, key)) = movement_key(event.key_code()) {
+        let mut sets = pressed.get();
+        *key(&mut sets[set]) = down;
+        pressed.set(sets);
     }
 }
 
-fn handle_keyup_event(event: web_sys::KeyboardEvent, pressed: &Rc<Cell<PressedKeys>>) {
-    if event.key_code() == KeyboardCodes::Left as u32 {
-        let mut keys = pressed.take();
-        keys.left = false;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Right as u32 {
-        let mut keys = pressed.take();
-        keys.right = false;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Up as u32 {
-        let mut keys = pressed.take();
-        keys.up = false;
-        pressed.set(keys);
-    } else if event.key_code() == KeyboardCodes::Down as u32 {
-        let mut keys = pressed.take();
-        keys.down = false;
-        pressed.set(keys);
+// The keys of every hero. Alone, herpooles walks with either set.
+pub fn player_keys(sets: KeySets, players: usize) -> Vec<PressedKeys> {
+    if players == 1 {
+        let [wasd, arrows] = sets;
+        vec![PressedKeys {
+            right: wasd.right || arrows.right,
+            left: wasd.left || arrows.left,
+            up: wasd.up || arrows.up,
+            down: wasd.down || arrows.down,
+        }]
+    } else {
+        sets[..players].to_vec()
     }
 }
 
-pub fn add_key_events(pressed_keys: &Rc<Cell<PressedKeys>>, document: &web_sys::Document) {
+pub fn add_key_events(pressed_keys: &Rc<Cell<KeySets>>, document: &web_sys::Document) {
     // keydown
     let pressed_down_keys = pressed_keys.clone();
     let keydown_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
-        handle_keydown_event(event, &pressed_down_keys)
+        handle_movement_key(&event, &pressed_down_keys, true)
     }) as Box<dyn FnMut(_)>);
     document
         .add_event_listener_with_callback("keydown", keydown_closure.as_ref().unchecked_ref())
@@ -69,7 +94,7 @@ pub fn add_key_events(pressed_keys: &Rc<Cell<PressedKeys>>, document: &web_sys::
     // keyup
     let pressed_up_keys = pressed_keys.clone();
     let keyup_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
-        handle_keyup_event(event, &pressed_up_keys)
+        handle_movement_key(&event, &pressed_up_keys, false)
     }) as Box<dyn FnMut(_)>);
     document
         .add_event_listener_with_callback("keyup", keyup_closure.as_ref().unchecked_ref())
@@ -77,51 +102,233 @@ pub fn add_key_events(pressed_keys: &Rc<Cell<PressedKeys>>, document: &web_sys::
     keyup_closure.forget();
 }
 
-pub fn add_restart_event(document: &web_sys::Document) {
-    let location = document.location().unwrap();
-    let restart_closure =
-        Closure::wrap(Box::new(move || location.reload().unwrap()) as Box<dyn Fn()>);
-    let restart_button = document.get_element_by_id("restart").unwrap();
-    restart_button
-        .add_event_listener_with_callback("click", restart_closure.as_ref().unchecked_ref())
-        .unwrap();
-    restart_closure.forget();
+// Applies a screen action. A new run starts over without reloading the page, so the wasm
+// module and the loaded assets are kept.
+fn act(
+    action: Action,
+    screen: &Rc<RefCell<Screen>>,
+    heroes: &Rc<RefCell<Vec<Herpooles>>>,
+    controller: &Rc<RefCell<Controller>>,
+    scores: &Rc<RefCell<HighScores>>,
+    online: &Rc<Cell<bool>>,
+    document: &web_sys::Document,
+) {
+    // nothing to continue without a saved run
+    let saved = match action {
+        Action::Continue => match Snapshot::load() {
+            Some(snapshot) => Some(snapshot),
+            None => return,
+        },
+        _ => None,
+    };
+    let transition = match online.get() {
+        true => screen.borrow_mut().handle_online(action),
+        false => screen.borrow_mut().handle(action),
+    };
+    match transition {
+        Transition::None => {}
+        Transition::NewRun => {
+            // the same players again, by the same rules
+            let players = heroes.borrow().len();
+            *heroes.borrow_mut() = Herpooles::team(players);
+            let rules = controller.borrow().rules();
+            controller.borrow_mut().restart(players, rules);
+            Snapshot::clear();
+        }
+        Transition::Start { players, rules } => {
+            *heroes.borrow_mut() = Herpooles::team(players);
+            controller.borrow_mut().restart(players, rules);
+            Snapshot::clear();
+        }
+        Transition::Resume => {
+            if let Some(snapshot) = saved {
+                let (h, c) = snapshot.restore();
+                *heroes.borrow_mut() = h;
+                *controller.borrow_mut() = c;
+            }
+        }
+        Transition::NameEntered(name) => {
+            // the controller still holds the finished run
+            let c = controller.borrow();
+            let mut scores = scores.borrow_mut();
+            let rank = scores.insert(Entry {
+                name,
+                score: c.score,
+                level: c.level(),
+                duration: c.time,
+                date: js_sys::Date::now(),
+            });
+            scores.save();
+            screen.borrow_mut().set_rank(rank);
+            online::submit(&scores.last_name, &c);
+        }
+    }
+    update_play_pause_button(&screen.borrow(), document);
 }
 
-pub fn add_play_pause_control(
-    anim_id: Rc<Cell<i32>>,
-    p: Rc<RefCell<Option<Closure<dyn FnMut()>>>>,
+fn play_pause_button(document: &web_sys::Document) -> web_sys::HtmlInputElement {
+    document
+        .get_element_by_id("play-pause")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap()
+}
+
+fn update_play_pause_button(screen: &Screen, document: &web_sys::Document) {
+    let label = match screen {
+        Screen::Paused { .. } => "Start",
+        _ => "Pause",
+    };
+    play_pause_button(document).set_value(label);
+}
+
+// The keys and toolbar buttons that move between screens. What each one does depends on
+// the current screen, see Screen::handle, and on whether we are in a room or watching.
+pub fn add_screen_controls(
+    screen: &Rc<RefCell<Screen>>,
+    heroes: &Rc<RefCell<Vec<Herpooles>>>,
+    controller: &Rc<RefCell<Controller>>,
+    scores: &Rc<RefCell<HighScores>>,
+    online: &Rc<Cell<bool>>,
     document: &web_sys::Document,
 ) {
-    // get_element_by_id returns an Element which is not Copy
-    let play_pause_button = document.get_element_by_id("play-pause").unwrap();
-    let pp_button = play_pause_button.clone();
-
-    let play_pause_closure = Closure::wrap(Box::new(move || {
-        let html_input_button = pp_button
-            .clone() // Because closure is Fn, not FnOnce
-            .dyn_into::<web_sys::HtmlInputElement>()
-            .unwrap();
-        if anim_id.get() == 0 {
-            anim_id.set(request_animation_frame(p.borrow().as_ref().unwrap()));
-            html_input_button.set_value("Pause");
+    // keys
+    let (s, h, c, hs, o, d) = (
+        screen.clone(),
+        heroes.clone(),
+        controller.clone(),
+        scores.clone(),
+        online.clone(),
+        document.clone(),
+    );
+    let screen_key_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+        if let Some(action) = screen::key_action(event.key_code()) {
+            act(action, &s, &h, &c, &hs, &o, &d);
+        }
+    }) as Box<dyn FnMut(_)>);
+    document
+        .add_event_listener_with_callback("keydown", screen_key_closure.as_ref().unchecked_ref())
+        .unwrap();
+    screen_key_closure.forget();
+
+    // While a name is typed every key goes to it, captured on the window before the other
+    // key handlers so typing an M or a P does not mute or pause.
+    let (s, h, c, hs, o, d) = (
+        screen.clone(),
+        heroes.clone(),
+        controller.clone(),
+        scores.clone(),
+        online.clone(),
+        document.clone(),
+    );
+    let name_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+        if !matches!(*s.borrow(), Screen::EnterName { .. }) {
+            return;
+        }
+        event.stop_propagation();
+        let key = event.key();
+        let action = if key == "Enter" {
+            Action::Confirm
+        } else if key == "Backspace" {
+            event.prevent_default();
+            Action::Erase
         } else {
-            cancel_animation_frame(anim_id.get());
-            anim_id.set(0);
-            html_input_button.set_value("Start");
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !c.is_control() => Action::Type(c),
+                _ => return,
+            }
+        };
+        act(action, &s, &h, &c, &hs, &o, &d);
+    }) as Box<dyn FnMut(_)>);
+    window()
+        .add_event_listener_with_callback_and_bool(
+            "keydown",
+            name_closure.as_ref().unchecked_ref(),
+            true,
+        )
+        .unwrap();
+    name_closure.forget();
+
+    // buttons
+    for (id, action) in [("play-pause", Action::Pause), ("restart", Action::Restart)] {
+        let (s, h, c, hs, o, d) = (
+            screen.clone(),
+            heroes.clone(),
+            controller.clone(),
+            scores.clone(),
+            online.clone(),
+            document.clone(),
+        );
+        let button_closure =
+            Closure::wrap(Box::new(move || act(action, &s, &h, &c, &hs, &o, &d)) as Box<dyn Fn()>);
+        document
+            .get_element_by_id(id)
+            .unwrap()
+            .add_event_listener_with_callback("click", button_closure.as_ref().unchecked_ref())
+            .unwrap();
+        button_closure.forget();
+    }
+}
+
+// Pauses when the tab is hidden or the window loses focus. The keyup events are lost
+// while we are in the background, so the held keys are released here as well.
+pub fn add_auto_pause(
+    screen: &Rc<RefCell<Screen>>,
+    pressed_keys: &Rc<Cell<KeySets>>,
+    document: &web_sys::Document,
+) {
+    let screen = screen.clone();
+    let pressed_keys = pressed_keys.clone();
+    let doc = document.clone();
+    let auto_pause = Rc::new(move || {
+        pressed_keys.set(KeySets::default());
+        screen.borrow_mut().pause();
+        update_play_pause_button(&screen.borrow(), &doc);
+    });
+    // blur
+    let on_blur = auto_pause.clone();
+    let blur_closure = Closure::wrap(Box::new(move || on_blur()) as Box<dyn Fn()>);
+    window()
+        .add_event_listener_with_callback("blur", blur_closure.as_ref().unchecked_ref())
+        .unwrap();
+    blur_closure.forget();
+    // visibilitychange fires for both hiding and showing the tab
+    let hidden_doc = document.clone();
+    let visibility_closure = Closure::wrap(Box::new(move || {
+        if hidden_doc.hidden() {
+            auto_pause();
         }
-    }) as Box<dyn Fn()>); // no FnMut needed
-    play_pause_button
-        .add_event_listener_with_callback("click", play_pause_closure.as_ref().unchecked_ref())
+    }) as Box<dyn Fn()>);
+    document
+        .add_event_listener_with_callback(
+            "visibilitychange",
+            visibility_closure.as_ref().unchecked_ref(),
+        )
         .unwrap();
-    play_pause_closure.forget();
+    visibility_closure.forget();
 }
 
-pub fn add_shoot(herpooles: &Rc<RefCell<Herpooles>>, document: &web_sys::Document) {
-    let herpooles = herpooles.clone();
+pub fn add_shoot(
+    screen: &Rc<RefCell<Screen>>,
+    heroes: &Rc<RefCell<Vec<Herpooles>>>,
+    document: &web_sys::Document,
+) {
+    let screen = screen.clone();
+    let heroes = heroes.clone();
     let shoot_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
-        if event.key_code() == KeyboardCodes::Spacebar as u32 {
-            herpooles.borrow_mut().fire_poo();
+        if !screen.borrow().is_playing() {
+            return;
+        }
+        let Some(player) = FIRE_KEYS.iter().position(|k| *k == event.key_code()) else {
+            return;
+        };
+        // the left shift is on the first hero's side of the keyboard
+        if player == 1 && event.location() != web_sys::KeyboardEvent::DOM_KEY_LOCATION_RIGHT {
+            return;
+        }
+        if let Some(h) = heroes.borrow_mut().get_mut(player) {
+            h.fire_poo();
         }
     }) as Box<dyn FnMut(_)>);
     document
@@ -130,25 +337,172 @@ pub fn add_shoot(herpooles: &Rc<RefCell<Herpooles>>, document: &web_sys::Documen
     shoot_closure.forget();
 }
 
-pub fn update_score(
-    current_score: &Rc<Cell<u32>>,
-    score_element: &Rc<web_sys::Element>, // using a Rc because I want to clone the Rc, not the
-    // inner Element
-    window: &web_sys::Window, // RefCell or Rc or Box ?
+pub fn add_resize_event(
+    canvas: &web_sys::HtmlCanvasElement,
+    ctx: &web_sys::CanvasRenderingContext2d,
+    hud: &Rc<RefCell<Hud>>,
+) {
+    let canvas = canvas.clone();
+    let ctx = ctx.clone();
+    let hud = hud.clone();
+    let resize_closure = Closure::wrap(Box::new(move || {
+        viewport::fit(&canvas, &ctx);
+        hud.borrow_mut().fit();
+    }) as Box<dyn Fn()>);
+    window()
+        .add_event_listener_with_callback("resize", resize_closure.as_ref().unchecked_ref())
+        .unwrap();
+    resize_closure.forget();
+}
+
+fn input_element(document: &web_sys::Document, id: &str) -> web_sys::HtmlInputElement {
+    document
+        .get_element_by_id(id)
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap()
+}
+
+// Stores the changed settings and applies them to the sound manager.
+fn change_settings(
+    settings: &Rc<Cell<Settings>>,
+    sounds: &SoundManager,
+    change: impl FnOnce(&mut Settings),
 ) {
-    // pass a new clone into the closure
-    // we need to pass a reference to the function
-    // because the main loop that calls it is FnMut, so it cannot move it
-    // outside of its envoronment.
-    let current_score = current_score.clone();
-    let score_element = score_element.clone();
-    // the closure to update the html element
-    let a = Closure::<dyn FnMut()>::new(move || {
-        score_element.set_inner_html(&current_score.get().to_string());
+    let mut s = settings.get();
+    change(&mut s);
+    settings.set(s);
+    s.save();
+    sounds.apply(&s);
+}
+
+fn add_volume_slider(
+    id: &str,
+    settings: &Rc<Cell<Settings>>,
+    sounds: &Rc<SoundManager>,
+    document: &web_sys::Document,
+    volume: fn(&mut Settings) -> &mut f32,
+) {
+    let slider = input_element(document, id);
+    slider.set_value(&volume(&mut settings.get()).to_string());
+    let settings = settings.clone();
+    let sounds = sounds.clone();
+    let input = slider.clone();
+    let slider_closure = Closure::wrap(Box::new(move || {
+        let value = input.value().parse::<f32>().unwrap_or(0.0);
+        change_settings(&settings, &sounds, |s| *volume(s) = value);
+    }) as Box<dyn Fn()>);
+    slider
+        .add_event_listener_with_callback("input", slider_closure.as_ref().unchecked_ref())
+        .unwrap();
+    slider_closure.forget();
+}
+
+pub fn add_settings_controls(
+    settings: &Rc<Cell<Settings>>,
+    sounds: &Rc<SoundManager>,
+    document: &web_sys::Document,
+) {
+    add_volume_slider("master-volume", settings, sounds, document, |s| {
+        &mut s.master
     });
-    // call the closure every 2 seconds
-    window
-        .set_interval_with_callback_and_timeout_and_arguments_0(a.as_ref().unchecked_ref(), 2000)
+    add_volume_slider("music-volume", settings, sounds, document, |s| &mut s.music);
+    add_volume_slider("effects-volume", settings, sounds, document, |s| {
+        &mut s.effects
+    });
+
+    // mute checkbox
+    let mute_checkbox = input_element(document, "mute");
+    mute_checkbox.set_checked(settings.get().muted);
+    let (mute_settings, mute_sounds, checkbox) =
+        (settings.clone(), sounds.clone(), mute_checkbox.clone());
+    let mute_closure = Closure::wrap(Box::new(move || {
+        let muted = checkbox.checked();
+        change_settings(&mute_settings, &mute_sounds, |s| s.muted = muted);
+    }) as Box<dyn Fn()>);
+    mute_checkbox
+        .add_event_listener_with_callback("change", mute_closure.as_ref().unchecked_ref())
+        .unwrap();
+    mute_closure.forget();
+
+    // M toggles the mute and keeps the checkbox in sync
+    let (key_settings, key_sounds) = (settings.clone(), sounds.clone());
+    let mute_key_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+        if event.key_code() == KeyboardCodes::M as u32 {
+            change_settings(&key_settings, &key_sounds, |s| s.muted = !s.muted);
+            mute_checkbox.set_checked(key_settings.get().muted);
+        }
+    }) as Box<dyn FnMut(_)>);
+    document
+        .add_event_listener_with_callback("keydown", mute_key_closure.as_ref().unchecked_ref())
+        .unwrap();
+    mute_key_closure.forget();
+}
+
+// Fills the theme picker with the built-in themes, plus the current one if it came from
+// a JSON file, and switches the skin when another one is picked.
+pub fn add_theme_select(skin: &Rc<RefCell<Skin>>, document: &web_sys::Document) {
+    let select = document
+        .get_element_by_id("theme")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlSelectElement>()
+        .unwrap();
+    let current = skin.borrow().theme.name.clone();
+    let mut names: Vec<&str> = Theme::BUILT_IN.to_vec();
+    if !names.contains(&current.as_str()) {
+        names.push(&current);
+    }
+    for name in names {
+        let option = document.create_element("option").unwrap();
+        option.set_attribute("value", name).unwrap();
+        option.set_text_content(Some(name));
+        select.append_child(&option).unwrap();
+    }
+    select.set_value(&current);
+
+    let skin = skin.clone();
+    let input = select.clone();
+    let theme_closure = Closure::wrap(Box::new(move || {
+        // the custom theme is already in use, it cannot be picked again after switching away
+        let Some(theme) = Theme::built_in(&input.value()) else {
+            return;
+        };
+        theme.save();
+        *skin.borrow_mut() = Skin::new(theme);
+    }) as Box<dyn Fn()>);
+    select
+        .add_event_listener_with_callback("change", theme_closure.as_ref().unchecked_ref())
+        .unwrap();
+    theme_closure.forget();
+}
+
+// Offers the statistics of the current run, or of the one that just ended, as a JSON file.
+fn download_stats(controller: &Controller, document: &web_sys::Document) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(&controller.stats.to_json()));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("application/json");
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let link = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    link.set_href(&url);
+    link.set_download(&format!("herpooles-stats-{}.json", controller.replay.seed));
+    link.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+pub fn add_stats_export(controller: &Rc<RefCell<Controller>>, document: &web_sys::Document) {
+    let (c, d) = (controller.clone(), document.clone());
+    let export_closure = Closure::wrap(Box::new(move || {
+        if let Err(e) = download_stats(&c.borrow(), &d) {
+            log!("could not export the stats: {:?}", e);
+        }
+    }) as Box<dyn Fn()>);
+    document
+        .get_element_by_id("export-stats")
+        .unwrap()
+        .add_event_listener_with_callback("click", export_closure.as_ref().unchecked_ref())
         .unwrap();
-    a.forget();
+    export_closure.forget();
 }
@@ -0,0 +1,78 @@
+use crate::game::World;
+use crate::geometry::Point;
+
+// Fraction of the view around its centre in which the target can move without the camera
+// following.
+const DEAD_ZONE: f32 = 0.3;
+// How fast the camera catches up, per second. Higher is snappier.
+const SMOOTHING: f32 = 6.0;
+
+// The part of the world shown on the canvas. x and y are the world coordinates of the
+// top left corner of the view.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// Where the camera should be on one axis to keep the target inside the dead zone,
+// clamped so the view does not go past the world, or centred when the world is smaller.
+fn follow_axis(camera: f32, view: f32, target: f32, world: f32) -> f32 {
+    let half_zone = view * DEAD_ZONE / 2.0;
+    let centre = camera + view / 2.0;
+    let wanted = if target < centre - half_zone {
+        target + half_zone - view / 2.0
+    } else if target > centre + half_zone {
+        target - half_zone - view / 2.0
+    } else {
+        camera
+    };
+    if world <= view {
+        (world - view) / 2.0
+    } else {
+        wanted.clamp(0.0, world - view)
+    }
+}
+
+impl Camera {
+    pub fn new(width: f32, height: f32) -> Camera {
+        Camera {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        }
+    }
+
+    // Eases towards keeping the target in the dead zone. dt in seconds.
+    pub fn follow(&mut self, target: Point, world: &World, dt: f32) {
+        let x = follow_axis(self.x, self.width, target.x, world.width);
+        let y = follow_axis(self.y, self.height, target.y, world.height);
+        let t = 1.0 - (-SMOOTHING * dt).exp();
+        self.x += (x - self.x) * t;
+        self.y += (y - self.y) * t;
+    }
+
+    pub fn to_screen(&self, p: Point) -> Point {
+        Point {
+            x: p.x - self.x,
+            y: p.y - self.y,
+        }
+    }
+
+    // Whether something at p, within margin pixels, shows up in the view.
+    pub fn is_visible(&self, p: Point, margin: f32) -> bool {
+        p.x >= self.x - margin
+            && p.x <= self.x + self.width + margin
+            && p.y >= self.y - margin
+            && p.y <= self.y + self.height + margin
+    }
+
+    // Makes everything drawn after this use world coordinates.
+    pub fn apply(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        ctx.translate(-f64::from(self.x), -f64::from(self.y))
+            .unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+use crate::callbacks::KeyboardCodes;
+use crate::game::{self, Controller, Herpooles, HIT_RADIUS};
+use crate::window;
+
+// Only built with the debug-overlay feature. Shown with Shift+D, or from the start with
+// ?debug in the page URL. ?zombies=<n> starts every level of a run played here with n
+// zombies, to see what drawing a big horde costs.
+
+// Weight of the newest sample in the smoothed counters.
+const SMOOTHING: f64 = 0.1;
+// The steering vectors are a fraction of a pixel per tick, drawn this many ticks long.
+const STEERING_SCALE: f32 = 30.0;
+
+fn now_ms() -> f64 {
+    window().performance().map_or(0.0, |p| p.now())
+}
+
+fn smooth(average: f64, sample: f64) -> f64 {
+    average + (sample - average) * SMOOTHING
+}
+
+pub struct DebugOverlay {
+    enabled: Rc<Cell<bool>>,
+    fps: f64,
+    // milliseconds per simulation tick
+    tick_ms: f64,
+    ticks_started: f64,
+    ticks: u32,
+    // milliseconds per frame spent drawing the world
+    draw_ms: f64,
+    draw_started: f64,
+    horde: Option<u16>,
+}
+
+impl DebugOverlay {
+    pub fn new(document: &web_sys::Document) -> DebugOverlay {
+        let params = window()
+            .location()
+            .search()
+            .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search))
+            .ok();
+        let from_url = params.as_ref().is_some_and(|p| p.has("debug"));
+        let horde = params
+            .and_then(|p| p.get("zombies"))
+            .and_then(|n| n.parse().ok());
+        let enabled = Rc::new(Cell::new(from_url));
+        let toggle = enabled.clone();
+        let toggle_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            if event.shift_key() && event.key_code() == KeyboardCodes::D as u32 {
+                toggle.set(!toggle.get());
+            }
+        }) as Box<dyn FnMut(_)>);
+        document
+            .add_event_listener_with_callback("keydown", toggle_closure.as_ref().unchecked_ref())
+            .unwrap();
+        toggle_closure.forget();
+        DebugOverlay {
+            enabled,
+            fps: 0.0,
+            tick_ms: 0.0,
+            ticks_started: 0.0,
+            ticks: 0,
+            draw_ms: 0.0,
+            draw_started: 0.0,
+            horde,
+        }
+    }
+
+    // elapsed in seconds since the previous frame
+    pub fn frame(&mut self, elapsed: f64) {
+        if elapsed > 0.0 {
+            self.fps = smooth(self.fps, 1.0 / elapsed);
+        }
+    }
+
+    pub fn begin_ticks(&mut self) {
+        self.ticks_started = now_ms();
+        self.ticks = 0;
+    }
+
+    pub fn tick_done(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn end_ticks(&mut self) {
+        if self.ticks > 0 {
+            let per_tick = (now_ms() - self.ticks_started) / f64::from(self.ticks);
+            self.tick_ms = smooth(self.tick_ms, per_tick);
+        }
+    }
+
+    pub fn begin_draw(&mut self) {
+        self.draw_started = now_ms();
+    }
+
+    pub fn end_draw(&mut self) {
+        self.draw_ms = smooth(self.draw_ms, now_ms() - self.draw_started);
+    }
+
+    // The ?zombies count, if there is one.
+    pub fn horde(&self) -> Option<u16> {
+        self.horde
+    }
+
+    // Hitboxes, steering and trajectories, in world coordinates.
+    pub fn draw_world(
+        &self,
+        ctx: &web_sys::CanvasRenderingContext2d,
+        heroes: &[Herpooles],
+        controller: &Controller,
+    ) {
+        if !self.enabled.get() {
+            return;
+        }
+        let radius = f64::from(HIT_RADIUS);
+        ctx.save();
+        ctx.set_line_width(1.0);
+
+        // the poo cleanup bounds
+        let (min_x, min_y, max_x, max_y) = controller.world.poo_bounds();
+        ctx.set_stroke_style_str("orange");
+        ctx.stroke_rect(
+            min_x.into(),
+            min_y.into(),
+            (max_x - min_x).into(),
+            (max_y - min_y).into(),
+        );
+
+        let living: Vec<&Herpooles> = heroes.iter().filter(|h| h.is_alive()).collect();
+        for h in heroes {
+            circle(ctx, h.x, h.y, radius, "blue");
+        }
+        for z in controller.zombies().iter().filter(|z| z.anim.is_walking()) {
+            circle(ctx, z.x, z.y, radius, "magenta");
+            // towards the nearest living hero, as game::update steers
+            let nearest = living.iter().min_by(|a, b| {
+                let da = (a.x - z.x).powi(2) + (a.y - z.y).powi(2);
+                let db = (b.x - z.x).powi(2) + (b.y - z.y).powi(2);
+                da.total_cmp(&db)
+            });
+            if let Some(h) = nearest {
+                let step = game::steering(z, h, controller.speed()) * STEERING_SCALE;
+                line(ctx, z.x, z.y, z.x + step.x, z.y + step.y, "magenta");
+            }
+        }
+
+        for p in heroes.iter().flat_map(|h| h.poo()) {
+            circle(ctx, p.x, p.y, radius, "saddlebrown");
+            // where it splats if it hits nothing
+            let v = p.velocity();
+            let ticks_x = if v.x > 0.0 {
+                (max_x - p.x) / v.x
+            } else if v.x < 0.0 {
+                (min_x - p.x) / v.x
+            } else {
+                f32::INFINITY
+            };
+            let ticks_y = if v.y > 0.0 {
+                (max_y - p.y) / v.y
+            } else if v.y < 0.0 {
+                (min_y - p.y) / v.y
+            } else {
+                f32::INFINITY
+            };
+            let ticks = ticks_x.min(ticks_y);
+            line(
+                ctx,
+                p.x,
+                p.y,
+                p.x + v.x * ticks,
+                p.y + v.y * ticks,
+                "saddlebrown",
+            );
+        }
+        ctx.restore();
+    }
+
+    // Performance counters, in view coordinates under the HUD.
+    pub fn draw_stats(
+        &self,
+        ctx: &web_sys::CanvasRenderingContext2d,
+        heroes: &[Herpooles],
+        controller: &Controller,
+        particles: usize,
+    ) {
+        if !self.enabled.get() {
+            return;
+        }
+        let lines = [
+            format!("fps: {:.0}", self.fps),
+            format!("tick: {:.2} ms", self.tick_ms),
+            format!("draw: {:.2} ms", self.draw_ms),
+            format!("zombies: {}", controller.zombies().len()),
+            format!(
+                "poo: {}",
+                heroes.iter().map(|h| h.poo().len()).sum::<usize>()
+            ),
+            format!("particles: {}", particles),
+        ];
+        ctx.save();
+        ctx.set_fill_style_str("rgba(255, 255, 255, 0.8)");
+        ctx.fill_rect(8.0, 44.0, 150.0, 18.0 * lines.len() as f64 + 8.0);
+        ctx.set_fill_style_str("black");
+        ctx.set_font("14px monospace");
+        ctx.set_text_align("left");
+        ctx.set_text_baseline("top");
+        for (i, text) in lines.iter().enumerate() {
+            ctx.fill_text(text, 14.0, 50.0 + 18.0 * i as f64).unwrap();
+        }
+        ctx.restore();
+    }
+}
+
+fn circle(ctx: &web_sys::CanvasRenderingContext2d, x: f32, y: f32, radius: f64, color: &str) {
+    ctx.set_stroke_style_str(color);
+    ctx.begin_path();
+    ctx.arc(x.into(), y.into(), radius, 0.0, std::f64::consts::PI * 2.0)
+        .unwrap();
+    ctx.stroke();
+}
+
+fn line(ctx: &web_sys::CanvasRenderingContext2d, x1: f32, y1: f32, x2: f32, y2: f32, color: &str) {
+    ctx.set_stroke_style_str(color);
+    ctx.begin_path();
+    ctx.move_to(x1.into(), y1.into());
+    ctx.line_to(x2.into(), y2.into());
+    ctx.stroke();
+}
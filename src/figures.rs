@@ -0,0 +1,292 @@
+use std::f64::consts::PI;
+
+use crate::animation::Animation;
+use crate::game::Direction;
+use crate::theme::{HerpoolesProportions, Theme, ZombieProportions};
+use web_sys::Path2d;
+
+// Leg positions cached per walk cycle. The legs snap between them, which is not visible
+// at the speed the characters walk.
+const WALK_FRAMES: usize = 8;
+
+const ELBOW_RELATIVE_POINT: f64 = 0.5; // ratio
+
+// Herpooles' body parts, scaled.
+struct Sizes {
+    head_radius: f64,
+    body_height: f64,
+    leg_height: f64,
+    arm_width: f64,
+    crown_width: f64,
+    // gap between the head and the crown
+    crown_height: f64,
+    neck_height: f64,
+}
+
+impl Sizes {
+    fn new(p: &HerpoolesProportions) -> Sizes {
+        let body_height = p.body_height * p.scale;
+        Sizes {
+            head_radius: p.head_radius * p.scale,
+            body_height,
+            leg_height: p.leg_height * p.scale,
+            arm_width: p.arm_width * p.scale,
+            crown_width: p.crown_width * p.scale,
+            crown_height: p.scale,
+            neck_height: body_height * 0.2,
+        }
+    }
+
+    fn feet(&self) -> f64 {
+        self.head_radius + self.body_height + self.leg_height
+    }
+}
+
+fn zombie_feet(p: &ZombieProportions) -> f64 {
+    p.head_radius + p.body_height + p.leg_height
+}
+
+fn bearing_index(bearing: Direction) -> usize {
+    match bearing {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    }
+}
+
+// Leg offset in [-1, 1] of a cached walk frame, see Animation::leg_swing.
+fn frame_swing(frame: usize) -> f64 {
+    (frame as f64 / WALK_FRAMES as f64 * 2.0 * PI).sin()
+}
+
+// The character shapes of a theme, built once as paths around the head at (0, 0) and
+// stamped at the character's position when drawing. Only the strokes that move with the
+// bearing and the walk cycle have one path per pose.
+pub struct Figures {
+    // below the head, where the figures fall over from when dying
+    pub herpooles_feet: f64,
+    pub zombie_feet: f64,
+    // per bearing, per walk frame
+    herpooles_body: Vec<Vec<Path2d>>,
+    crown: Path2d,
+    cape: Path2d,
+    // per walk frame
+    zombie: Vec<Path2d>,
+}
+
+impl Figures {
+    pub fn new(theme: &Theme) -> Figures {
+        let sizes = Sizes::new(&theme.herpooles);
+        let bearings = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+        Figures {
+            herpooles_feet: sizes.feet(),
+            zombie_feet: zombie_feet(&theme.zombie),
+            herpooles_body: bearings
+                .iter()
+                .map(|&bearing| {
+                    (0..WALK_FRAMES)
+                        .map(|frame| herpooles_body(&sizes, bearing, frame_swing(frame)))
+                        .collect()
+                })
+                .collect(),
+            crown: crown(&sizes),
+            cape: cape(&sizes),
+            zombie: (0..WALK_FRAMES)
+                .map(|frame| zombie(&theme.zombie, frame_swing(frame)))
+                .collect(),
+        }
+    }
+
+    pub fn herpooles_body(&self, bearing: Direction, anim: &Animation) -> &Path2d {
+        &self.herpooles_body[bearing_index(bearing)][anim.walk_frame(WALK_FRAMES)]
+    }
+
+    pub fn crown(&self) -> &Path2d {
+        &self.crown
+    }
+
+    pub fn cape(&self) -> &Path2d {
+        &self.cape
+    }
+
+    pub fn zombie(&self, anim: &Animation) -> &Path2d {
+        &self.zombie[anim.walk_frame(WALK_FRAMES)]
+    }
+}
+
+fn path() -> Path2d {
+    Path2d::new().expect("should create a Path2D")
+}
+
+fn herpooles_body(sizes: &Sizes, bearing: Direction, swing: f64) -> Path2d {
+    let p = path();
+    // how far the feet move back and forth in the walk cycle
+    let stride = swing * sizes.arm_width / 4.0;
+    // Draw the head
+    p.move_to(0.0, sizes.head_radius);
+    p.line_to(-sizes.head_radius, sizes.head_radius / 2.0);
+    p.line_to(-sizes.head_radius, -sizes.head_radius);
+    p.line_to(sizes.head_radius, -sizes.head_radius);
+    p.line_to(sizes.head_radius, sizes.head_radius / 2.0);
+    p.line_to(0.0, sizes.head_radius);
+    // Draw the body
+    p.move_to(0.0, sizes.head_radius);
+    p.line_to(0.0, sizes.head_radius + sizes.body_height);
+    // Draw the legs
+    p.move_to(
+        -sizes.arm_width / 4.0,
+        sizes.head_radius + sizes.body_height,
+    );
+    p.line_to(-sizes.arm_width / 4.0 + stride, sizes.feet());
+    p.move_to(sizes.arm_width / 4.0, sizes.head_radius + sizes.body_height);
+    p.line_to(sizes.arm_width / 4.0 - stride, sizes.feet());
+    // Draw the arms until the elbow, at the height of the shoulders
+    let shoulders = sizes.head_radius + sizes.neck_height;
+    p.move_to(0.0, shoulders);
+    p.line_to(-sizes.arm_width / 2.0, shoulders);
+    p.move_to(0.0, shoulders);
+    p.line_to(sizes.arm_width / 2.0, shoulders);
+    // Draw the hands
+    let elbow = sizes.arm_width * ELBOW_RELATIVE_POINT;
+    let (left_end_x, right_end_x, left_end_y, right_end_y) = match bearing {
+        Direction::East => (
+            elbow,
+            sizes.arm_width,
+            sizes.head_radius + sizes.body_height * 0.1, // TODO: parameterize the tilt of the hands
+            sizes.head_radius + sizes.body_height * 0.1,
+        ),
+        Direction::West => (
+            -sizes.arm_width, // until the end of the hand
+            -elbow,
+            sizes.head_radius + sizes.body_height * 0.1,
+            sizes.head_radius + sizes.body_height * 0.1,
+        ),
+        Direction::South => (
+            -elbow - 3.5, // until the elbow + something extra to not overlap his cap
+            elbow + 3.5,
+            sizes.head_radius + sizes.body_height * 0.5,
+            sizes.head_radius + sizes.body_height * 0.5,
+        ),
+        Direction::North => (
+            -elbow, // until the elbow
+            elbow,
+            sizes.head_radius - sizes.body_height * 0.1,
+            sizes.head_radius - sizes.body_height * 0.1,
+        ),
+    };
+    // left hand
+    p.move_to(-elbow, shoulders);
+    p.line_to(left_end_x, left_end_y);
+    // right hand
+    p.move_to(elbow, shoulders);
+    p.line_to(right_end_x, right_end_y);
+    p.close_path();
+    p
+}
+
+fn crown(sizes: &Sizes) -> Path2d {
+    let p = path();
+    let crown_height = sizes.crown_height;
+    let crown_width = sizes.crown_width;
+    let half_base = crown_width / 6.0;
+    let crown_bottom = -sizes.head_radius - crown_height;
+    let crown_top = crown_bottom - crown_width / 2.0;
+    let extra_height = crown_width * 0.1;
+    let crown_start_x = -crown_width / 2.0;
+    // Left triangle of the crown
+    p.move_to(crown_start_x, crown_bottom);
+    p.line_to(crown_start_x + half_base, crown_top);
+    p.line_to(crown_start_x + 2.0 * half_base, crown_bottom);
+    p.line_to(crown_start_x, crown_bottom);
+    // Middle triangle
+    p.move_to(crown_start_x + 2.0 * half_base, crown_bottom);
+    p.line_to(crown_start_x + 3.0 * half_base, crown_top - extra_height);
+    p.line_to(crown_start_x + 4.0 * half_base, crown_bottom);
+    p.line_to(crown_start_x + 2.0 * half_base, crown_bottom);
+    // Right triangle
+    p.move_to(crown_start_x + 4.0 * half_base, crown_bottom);
+    p.line_to(crown_start_x + 5.0 * half_base, crown_top);
+    p.line_to(crown_start_x + 6.0 * half_base, crown_bottom);
+    p.line_to(crown_start_x + 4.0 * half_base, crown_bottom);
+    p.close_path();
+    p
+}
+
+fn cape(sizes: &Sizes) -> Path2d {
+    let p = path();
+    let cape_width = sizes.arm_width; // elbow to albow
+    let cape_height = sizes.leg_height + sizes.body_height;
+    let cape_start_x = -cape_width / 2.0;
+    let cape_end_x = cape_start_x + cape_width;
+    let cape_top_y = sizes.head_radius + sizes.neck_height; // start at the shoulders
+    let cape_bottom_y = cape_top_y + sizes.body_height;
+    let left_tilt = cape_width * 0.1;
+
+    p.move_to(cape_start_x, cape_top_y);
+    p.line_to(cape_start_x - left_tilt, cape_bottom_y);
+    p.line_to(cape_end_x - left_tilt, cape_bottom_y);
+    p.line_to(cape_end_x, cape_top_y);
+    p.line_to(cape_start_x, cape_top_y);
+
+    let cape_control_x1 = cape_start_x + cape_width * 0.2;
+    let cape_control_y1 = cape_bottom_y + cape_height * 0.2;
+    let cape_control_x2 = cape_start_x + cape_width * 0.65;
+    let cape_control_y2 = cape_bottom_y;
+
+    p.move_to(cape_start_x - left_tilt, cape_bottom_y);
+    p.bezier_curve_to(
+        cape_control_x1,
+        cape_control_y1,
+        cape_control_x2,
+        cape_control_y2,
+        cape_end_x - left_tilt,
+        cape_bottom_y,
+    );
+    p
+}
+
+fn zombie(proportions: &ZombieProportions, swing: f64) -> Path2d {
+    let p = path();
+    let (head, body, arm) = (
+        proportions.head_radius,
+        proportions.body_height,
+        proportions.arm_width,
+    );
+    let stride = swing * arm / 2.0;
+    // Draw the head
+    p.arc(0.0, 0.0, head, 0.0, PI * 2.0).unwrap();
+    // Draw the body
+    p.move_to(0.0, head);
+    p.line_to(0.0, head + body);
+    // Draw the legs
+    p.move_to(0.0, head + body);
+    p.line_to(-arm / 2.0 + stride, zombie_feet(proportions));
+    p.move_to(0.0, head + body);
+    p.line_to(arm / 2.0 - stride, zombie_feet(proportions));
+    // Draw the arms
+    p.move_to(-arm / 2.0, head + body / 2.0);
+    p.line_to(-arm, head + body / 2.0);
+    p.move_to(arm / 2.0, head + body / 2.0);
+    p.line_to(arm, head + body / 2.0);
+    p.close_path();
+    p
+}
+
+// A theme and the figures built from it, replaced together when the theme changes.
+pub struct Skin {
+    pub theme: Theme,
+    pub figures: Figures,
+}
+
+impl Skin {
+    pub fn new(theme: Theme) -> Skin {
+        let figures = Figures::new(&theme);
+        Skin { theme, figures }
+    }
+}
@@ -1,70 +1,218 @@
+use crate::animation::Animation;
+use crate::assets::Assets;
+use crate::camera::Camera;
+use crate::figures::Skin;
 use crate::geometry;
 use crate::render;
+use crate::replay::Replay;
+use crate::stats::{Cause, Stats};
+use crate::versus::{self, STUN};
 use crate::PressedKeys;
-use js_sys::Date;
-use rand;
-use std::cell::Cell;
-use std::cell::RefCell;
-use std::rc::Rc;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+// The area herpooles can walk in, zombies spawn outside of it. It grows with the levels
+// and can be larger than the canvas, the camera follows herpooles around.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct World {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl World {
+    pub fn for_level(level: u16) -> World {
+        // 10% larger every level, up to twice the size of the first one
+        let scale = (1.0 + 0.1 * f32::from(level - 1)).min(2.0);
+        World {
+            width: 1000.0 * scale,
+            height: 800.0 * scale,
+        }
+    }
+
+    // Whether a point is on the edge herpooles cannot walk past, or beyond it.
+    pub fn at_edge(&self, x: f32, y: f32) -> bool {
+        x <= 0.0 || y <= 0.0 || x >= self.width || y >= self.height
+    }
+
+    // Poo that flies out of this rectangle splats and is cleaned up,
+    // as (min_x, min_y, max_x, max_y).
+    pub fn poo_bounds(&self) -> (f32, f32, f32, f32) {
+        (2.0, 2.0, self.width - 3.0, self.height - 3.0)
+    }
+}
+
+// The simulation runs in fixed steps, whatever the display refresh rate.
+pub const TICK: f64 = 1.0 / 60.0;
+
+// Heroes on one keyboard.
+pub const MAX_PLAYERS: usize = 2;
+// Distance under which a zombie reaches herpooles or a poo hits a zombie.
+pub const HIT_RADIUS: f32 = 20.0;
+// Seconds between two poo.
+const FIRE_INTERVAL: f64 = 0.5;
+// Seconds herpooles flashes once a zombie reached him, while he falls over.
+const CONTACT_FLASH: f64 = 0.3;
+
+// How the heroes of a run play: together, or against each other, see versus.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rules {
+    #[default]
+    Coop,
+    Versus,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Herpooles {
+    // 0 for the first player, picks the colour
+    #[serde(default)]
+    pub player: usize,
     pub x: f32, // pub needed to render
     pub y: f32,
-    dead: bool,
+    // zombies killed by his poo
+    #[serde(default)]
+    pub score: u32,
+    // rivals his poo hit, in versus
+    #[serde(default)]
+    pub stuns: u32,
+    // simulation time until which he can neither walk nor fire, after a rival's poo
+    #[serde(default)]
+    stunned_until: f64,
     poo: Vec<Poo>,
     pub bearing: Direction, // for render
+    // fire was pressed since the last update
+    fire_requested: bool,
+    // simulation time of the last poo
     instant_fired: f64,
+    pub anim: Animation,
+}
+
+impl Default for Herpooles {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Herpooles {
     pub fn new() -> Herpooles {
         Herpooles {
+            player: 0,
             x: 500.0,
             y: 500.0,
-            dead: true,
+            score: 0,
+            stuns: 0,
+            stunned_until: 0.0,
             poo: vec![],
             bearing: Direction::North,
-            instant_fired: Date::now(),
+            fire_requested: false,
+            instant_fired: 0.0,
+            anim: Animation::default(),
         }
     }
 
+    // The heroes of a run, side by side around where a single one starts.
+    pub fn team(players: usize) -> Vec<Herpooles> {
+        let middle = (players as f32 - 1.0) / 2.0;
+        (0..players)
+            .map(|player| Herpooles {
+                player,
+                x: 500.0 + 80.0 * (player as f32 - middle),
+                ..Herpooles::new()
+            })
+            .collect()
+    }
+
+    // Input handlers only ask to fire, the poo comes out on the next update so it is timed
+    // by the simulation clock.
     pub fn fire_poo(&mut self) {
+        self.fire_requested = true;
+    }
+
+    // Returns true if a poo came out.
+    fn try_fire(&mut self, now: f64) -> bool {
         // limit firing poo
-        if Date::now() - self.instant_fired > 500.0 {
+        let fired = self.fire_requested && now - self.instant_fired > FIRE_INTERVAL;
+        if fired {
             self.poo.push(Poo::new(&self.x, &self.y, self.bearing));
-            self.instant_fired = Date::now();
+            self.instant_fired = now;
         }
+        self.fire_requested = false;
+        fired
+    }
+
+    // From 0 right after firing to 1 when the next poo is ready.
+    pub fn reload_progress(&self, now: f64) -> f32 {
+        ((now - self.instant_fired) / FIRE_INTERVAL).clamp(0.0, 1.0) as f32
+    }
+
+    pub fn poo(&self) -> &[Poo] {
+        &self.poo
+    }
+
+    pub(crate) fn poo_mut(&mut self) -> &mut [Poo] {
+        &mut self.poo
+    }
+
+    pub fn is_stunned(&self, now: f64) -> bool {
+        now < self.stunned_until
     }
 
     pub fn is_alive(&self) -> bool {
-        !self.dead
+        self.anim.is_walking()
     }
 
-    fn color(&self) -> &str {
-        match self.dead {
-            true => "red",
-            false => "green",
-        }
+    // Dead, and done falling over.
+    pub fn is_gone(&self) -> bool {
+        self.anim.is_done()
     }
 }
 
+// The run is over once every hero is gone.
+pub fn all_gone(heroes: &[Herpooles]) -> bool {
+    heroes.iter().all(|h| h.is_gone())
+}
+
+// Every hero gone, or in versus as soon as there is a winner.
+pub fn run_over(heroes: &[Herpooles], controller: &Controller) -> bool {
+    all_gone(heroes) || versus::outcome(heroes, controller).is_some()
+}
+
+// Where the camera and the ears are: between the living heroes, or the fallen ones once
+// there are none.
+pub fn focus(heroes: &[Herpooles]) -> geometry::Point {
+    let living: Vec<&Herpooles> = heroes.iter().filter(|h| h.is_alive()).collect();
+    let group = if living.is_empty() {
+        heroes.iter().collect()
+    } else {
+        living
+    };
+    let n = group.len().max(1) as f32;
+    geometry::Point {
+        x: group.iter().map(|h| h.x).sum::<f32>() / n,
+        y: group.iter().map(|h| h.y).sum::<f32>() / n,
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Zombie {
     pub x: f32,
     pub y: f32,
-    walking: bool,
+    pub anim: Animation,
+    // whether it has walked into the arena from its spawn point
+    entered: bool,
 }
 
 impl Zombie {
-    pub fn new(level: u16) -> Zombie {
-        let xr = rand::random::<f32>() - 0.5;
-        let yr = rand::random::<f32>() - 0.5;
-        let x_variance = 1000.0;
+    pub fn new(level: u16, world: &World, rng: &mut ChaCha12Rng) -> Zombie {
+        let xr = rng.gen::<f32>() - 0.5;
+        let yr = rng.gen::<f32>() - 0.5;
+        let x_variance = world.width;
         let y_variance = 400.0;
-        let x_start = 500.0;
+        let x_start = world.width / 2.0;
         let y_start = if level > 1 {
             if xr > 0.3 {
-                1000.0 // from south
+                world.height + 200.0 // from south
             } else {
                 -200.0 // from north
             }
@@ -74,20 +222,13 @@ impl Zombie {
         Zombie {
             x: x_start + xr * x_variance,
             y: y_start + yr * y_variance,
-            walking: true,
-        }
-    }
-
-    // no effect since we clean dirty zombies in the same frame
-    fn color(&self) -> &str {
-        match self.walking {
-            true => "grey",
-            false => "yellow",
+            anim: Animation::default(),
+            entered: false,
         }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Poo {
     pub x: f32,
     pub y: f32,
@@ -100,50 +241,170 @@ impl Poo {
         Poo {
             x: *x,
             y: *y,
-            direction: direction,
+            direction,
             must_clean: false,
         }
     }
+
+    // How far it flies in one tick.
+    pub fn velocity(&self) -> geometry::Vector {
+        // find unit vector of orbit
+        // TODO: maybe store the unit vector in the poo instead of the direction.
+        let (next_x, next_y) = match self.direction {
+            Direction::North => (self.x, self.y - 1.0),
+            Direction::East => (self.x + 1.0, self.y),
+            Direction::South => (self.x, self.y + 1.0),
+            Direction::West => (self.x - 1.0, self.y),
+        };
+        let direction_vec = geometry::Vector::new(
+            geometry::Point {
+                x: self.x,
+                y: self.y,
+            },
+            geometry::Point {
+                x: next_x,
+                y: next_y,
+            },
+        );
+        let poo_speed = 0.6;
+        direction_vec.unit_vec() * poo_speed
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Controller {
     level: u16,
     num_zombies: u16,
     speed: f32,
-    zombies: Box<Vec<Zombie>>,
-    zombie_kill_sound: web_sys::HtmlAudioElement,
-    // Cell because it is mutated when counting the score.
-    pub score: Rc<Cell<u32>>,
+    zombies: Vec<Zombie>,
+    pub world: World,
+    // simulation seconds since the start of the run
+    pub time: f64,
+    pub score: u32,
+    pub stats: Stats,
+    // All the randomness of a run comes from here, so the seed and the inputs are enough
+    // to play it again, see replay::simulate.
+    rng: ChaCha12Rng,
+    rules: Rules,
+    // zombies every level starts with when set, for the debug overlay to measure a big horde
+    horde: Option<u16>,
+    // the inputs of the run so far, recorded by the main loop, with a copy of the rules
+    pub replay: Replay,
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new(1, Rules::Coop)
+    }
 }
 
 impl Controller {
-    pub fn new(zombies: Box<Vec<Zombie>>) -> Self {
-        let audio_zombie_kill = web_sys::HtmlAudioElement::new_with_src("resources/zombie-die.wav")
-            .expect("Could not load wav");
+    pub fn new(players: usize, rules: Rules) -> Self {
+        Self::with_seed(rand::random(), players, rules)
+    }
+
+    pub fn with_seed(seed: u64, players: usize, rules: Rules) -> Self {
+        let level = 1;
+        let world = World::for_level(level);
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let mut stats = Stats::default();
+        stats.level_started(level);
+        let mut replay = Replay::new(seed, players);
+        replay.rules = rules;
         Self {
-            level: 1,
+            level,
             num_zombies: 10,
             speed: 0.5,
-            zombies: zombies,
-            zombie_kill_sound: audio_zombie_kill,
-            score: Rc::new(Cell::new(0)),
+            zombies: (0..10)
+                .map(|_| Zombie::new(level, &world, &mut rng))
+                .collect(),
+            world,
+            time: 0.0,
+            score: 0,
+            stats,
+            rng,
+            rules,
+            horde: None,
+            replay,
         }
     }
 
-    pub fn check(&mut self) {
-        if self.zombies.len() == 0 {
-            self.reset()
+    // The controller a replay starts from.
+    pub fn for_replay(replay: &Replay) -> Self {
+        let mut controller = Self::with_seed(replay.seed, replay.players, replay.rules);
+        if let Some(n) = replay.horde {
+            controller.crowd(n);
         }
+        controller
+    }
+
+    pub fn level(&self) -> u16 {
+        self.level
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn zombies(&self) -> &[Zombie] {
+        &self.zombies
+    }
+
+    pub(crate) fn zombies_mut(&mut self) -> &mut [Zombie] {
+        &mut self.zombies
+    }
+
+    // Zombie steps per tick on this level.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // Zombies still to kill in this level.
+    pub fn zombies_left(&self) -> usize {
+        self.zombies.iter().filter(|z| z.anim.is_walking()).count()
+    }
+
+    // Moves on to the next level once the horde is gone. Returns true if it did.
+    pub fn check(&mut self) -> bool {
+        if self.zombies.is_empty() {
+            self.next_level();
+            return true;
+        }
+        false
     }
 
-    fn reset(&mut self) {
-        self.level = self.level + 1;
-        self.num_zombies = self.level * 10;
-        self.speed = self.speed + 0.1;
+    // Starts every level with n zombies, this one too. Only before the first tick of a run,
+    // the replay keeps n to play it again.
+    pub fn crowd(&mut self, n: u16) {
+        self.horde = Some(n);
+        self.replay.horde = Some(n);
+        self.num_zombies = n;
+        let (level, world, rng) = (self.level, self.world, &mut self.rng);
         self.zombies
-            .resize_with(self.num_zombies.into(), || Zombie::new(self.level));
+            .resize_with(n.into(), || Zombie::new(level, &world, rng));
+    }
+
+    // Back to the first level with a fresh horde and a new seed, as big as before.
+    pub fn restart(&mut self, players: usize, rules: Rules) {
+        let horde = self.horde;
+        *self = Controller::new(players, rules);
+        if let Some(n) = horde {
+            self.crowd(n);
+        }
+        log!("restart");
+    }
+
+    fn next_level(&mut self) {
+        self.level += 1;
+        self.num_zombies = self.horde.unwrap_or(self.level * 10);
+        self.speed += 0.1;
+        self.world = World::for_level(self.level);
+        self.stats.level_started(self.level);
+        let (level, world, rng) = (self.level, self.world, &mut self.rng);
+        self.zombies
+            .resize_with(self.num_zombies.into(), || Zombie::new(level, &world, rng));
         log!(
-            "reset level: {}, num_zombies = {}, speed: {}",
+            "next level: {}, num_zombies = {}, speed: {}",
             self.level,
             self.num_zombies,
             self.speed
@@ -151,7 +412,17 @@ impl Controller {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+// What happened during a step, for the parts of the game outside the simulation (sound, ...).
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    ZombieKilled { x: f32, y: f32 },
+    HerpoolesDied { x: f32, y: f32 },
+    ZombieEntered { x: f32, y: f32 },
+    PooSplat { x: f32, y: f32 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
     North,
     East,
@@ -159,65 +430,83 @@ pub enum Direction {
     West,
 }
 
+// What the player did before a tick: the held keys, whether fire was asked for and where
+// herpooles faces.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub keys: PressedKeys,
+    pub fire: bool,
+    pub bearing: Direction,
+}
+
+impl Input {
+    pub fn capture(h: &Herpooles, keys: PressedKeys) -> Input {
+        Input {
+            keys,
+            fire: h.fire_requested,
+            bearing: h.bearing,
+        }
+    }
+
+    // Puts herpooles back in the state the input was captured from.
+    pub fn apply(&self, h: &mut Herpooles) {
+        h.fire_requested = self.fire;
+        h.bearing = self.bearing;
+    }
+}
+
+fn distance(h: &Herpooles, z: &Zombie) -> f32 {
+    ((h.x - z.x).powi(2) + (h.y - z.y).powi(2)).sqrt()
+}
+
 fn zombies_reached(h: &Herpooles, z: &Zombie) -> bool {
     let d = (h.x - z.x) * (h.x - z.x) + (h.y - z.y) * (h.y - z.y);
     //log!("d: {}", d);
-    if d < 400.0 {
-        log!("herpooles dead!");
-    }
-    d < 400.0 // TODO: calculate based on herpooles and zombie area
+    z.anim.is_walking() && d < HIT_RADIUS * HIT_RADIUS // TODO: calculate based on herpooles and zombie area
 }
 
 fn hit_zombie(p: &Poo, z: &Zombie) -> bool {
-    if p.must_clean {
+    if p.must_clean || !z.anim.is_walking() {
         false
     } else {
         let d = (p.x - z.x) * (p.x - z.x) + (p.y - z.y) * (p.y - z.y);
-        d < 400.0 // TODO: calculate based on area
+        d < HIT_RADIUS * HIT_RADIUS // TODO: calculate based on area
     }
 }
 
-// pass zombie speed from the controller
-fn move_zombie(z: &mut Zombie, h: &Herpooles, zombie_speed: &f32) {
-    // apply A + d n.
-    // d is the speed, A the initial position, n the unit vector.
+// The step a zombie takes towards herpooles in one tick, before the disturbance.
+pub fn steering(z: &Zombie, h: &Herpooles, zombie_speed: f32) -> geometry::Vector {
     let zp = geometry::Point { x: z.x, y: z.y };
     let hp = geometry::Point { x: h.x, y: h.y };
     // vector z -> h
     let zh_vec = geometry::Vector::new(zp, hp);
-    let mv_vec: geometry::Vector = zh_vec.unit_vec() * *zombie_speed;
+    zh_vec.unit_vec() * zombie_speed
+}
+
+// pass zombie speed from the controller
+fn move_zombie(z: &mut Zombie, h: &Herpooles, zombie_speed: &f32, rng: &mut ChaCha12Rng) {
+    // apply A + d n.
+    // d is the speed, A the initial position, n the unit vector.
+    let zp = geometry::Point { x: z.x, y: z.y };
+    let mv_vec = steering(z, h, *zombie_speed);
     let pos: geometry::Point = zp + mv_vec;
     // some disturbance in zombie steps
-    let xr = rand::random::<f32>() - 0.5;
-    let yr = rand::random::<f32>() - 0.5;
+    let xr = rng.gen::<f32>() - 0.5;
+    let yr = rng.gen::<f32>() - 0.5;
     z.x = pos.x + xr;
     z.y = pos.y + yr;
+    z.anim.walk(*zombie_speed);
 }
 
 fn move_poo(p: &mut Poo) {
-    // find unit vector of orbit
-    // TODO: maybe store the unit vector in the poo instead of the direction.
-    let (p_next_x, p_next_y) = match p.direction {
-        Direction::North => (p.x, p.y - 1.0),
-        Direction::East => (p.x + 1.0, p.y),
-        Direction::South => (p.x, p.y + 1.0),
-        Direction::West => (p.x - 1.0, p.y),
-    };
-    let direction_vec = geometry::Vector::new(
-        geometry::Point { x: p.x, y: p.y },
-        geometry::Point {
-            x: p_next_x,
-            y: p_next_y,
-        },
-    );
-    let poo_speed = 0.6;
-    let mv_vec = direction_vec.unit_vec() * poo_speed;
-    p.x = p.x + mv_vec.x;
-    p.y = p.y + mv_vec.y;
-}
-
-pub fn move_herpooles(herpooles: &mut Herpooles, pressed_keys: &PressedKeys) {
-    if pressed_keys.right && herpooles.x < 1000.0 {
+    let mv_vec = p.velocity();
+    p.x += mv_vec.x;
+    p.y += mv_vec.y;
+}
+
+pub fn move_herpooles(herpooles: &mut Herpooles, pressed_keys: &PressedKeys, world: &World) {
+    let (x, y) = (herpooles.x, herpooles.y);
+    if pressed_keys.right && herpooles.x < world.width {
         herpooles.bearing = Direction::East;
         herpooles.x += 2.0;
     }
@@ -229,78 +518,210 @@ pub fn move_herpooles(herpooles: &mut Herpooles, pressed_keys: &PressedKeys) {
         herpooles.bearing = Direction::North;
         herpooles.y -= 2.0;
     }
-    if pressed_keys.down && herpooles.y < 800.0 {
+    if pressed_keys.down && herpooles.y < world.height {
         herpooles.bearing = Direction::South;
         herpooles.y += 2.0;
     }
+    let moved = (herpooles.x - x).abs() + (herpooles.y - y).abs();
+    herpooles.anim.walk(moved);
 }
 
-pub fn step(
-    ctx: &web_sys::CanvasRenderingContext2d,
-    h: &Rc<RefCell<Herpooles>>,
-    pressed_keys: &Rc<Cell<PressedKeys>>,
-    controller: &mut Controller,
-) {
-    // TODO: make static
-    let height = ctx.canvas().expect("get canvas").height() as f64;
-    let width = ctx.canvas().expect("get canvas").width() as f64;
-    ctx.clear_rect(1.0, 1.0, width - 3.0, height - 2.0);
+// One tick of a run with one input per hero, in order: the inputs, the simulation, then the
+// next level if the horde is gone and the run goes on. Whoever plays a run, here or again
+// from its replay, plays it through this.
+pub fn tick(heroes: &mut [Herpooles], inputs: &[Input], controller: &mut Controller) -> Vec<Event> {
+    for (h, input) in heroes.iter_mut().zip(inputs) {
+        input.apply(h);
+    }
+    let keys: Vec<PressedKeys> = inputs.iter().map(|i| i.keys).collect();
+    let events = update(heroes, &keys, controller);
+    if !run_over(heroes, controller) {
+        controller.check();
+    }
+    events
+}
 
-    let mut h_ref = h.borrow_mut();
-    let pressed_keys = pressed_keys.get();
+// Advances the simulation by one TICK. keys holds the keys of every hero, in order.
+pub fn update(
+    heroes: &mut [Herpooles],
+    keys: &[PressedKeys],
+    controller: &mut Controller,
+) -> Vec<Event> {
+    let mut events = vec![];
+    controller.time += TICK;
+    let now = controller.time;
     let zombies = &mut controller.zombies;
 
-    // move herpooles
-    move_herpooles(&mut h_ref, &pressed_keys);
-    // move zombies
-    zombies.iter_mut().for_each(|z| {
-        move_zombie(z, &h_ref, &controller.speed);
-    });
+    heroes.iter_mut().for_each(|h| h.anim.update(now));
+    zombies.iter_mut().for_each(|z| z.anim.update(now));
+    // clean zombies that are done dying
+    zombies.retain(|z| !z.anim.is_done());
+    if !heroes.iter().any(|h| h.is_alive()) {
+        return events; // only the animations go on
+    }
+
+    controller.stats.tick(TICK);
 
-    // An empty iterator returns false.
-    if !zombies.is_empty() {
-        h_ref.dead = zombies.iter().any(|z| zombies_reached(&h_ref, &z));
+    // move herpooles
+    for (h, pressed_keys) in heroes.iter_mut().zip(keys) {
+        if !h.is_alive() {
+            continue;
+        }
+        if h.is_stunned(now) {
+            h.fire_requested = false;
+            continue;
+        }
+        let (x, y) = (h.x, h.y);
+        move_herpooles(h, pressed_keys, &controller.world);
+        controller
+            .stats
+            .walked(((h.x - x).powi(2) + (h.y - y).powi(2)).sqrt());
+        if controller.world.at_edge(h.x, h.y) && !controller.world.at_edge(x, y) {
+            controller.stats.touched_edge();
+        }
+        if h.try_fire(now) {
+            controller.stats.fired();
+        }
+    }
+    // move zombies, each after the nearest living hero
+    zombies
+        .iter_mut()
+        .filter(|z| z.anim.is_walking())
+        .for_each(|z| {
+            let target = heroes
+                .iter()
+                .filter(|h| h.is_alive())
+                .min_by(|a, b| distance(a, z).total_cmp(&distance(b, z)))
+                .expect("a living hero");
+            move_zombie(z, target, &controller.speed, &mut controller.rng);
+            if !z.entered && (0.0..=controller.world.height).contains(&z.y) {
+                z.entered = true;
+                events.push(Event::ZombieEntered { x: z.x, y: z.y });
+            }
+        });
+    if let Some(nearest) = heroes
+        .iter()
+        .filter(|h| h.is_alive())
+        .flat_map(|h| {
+            zombies
+                .iter()
+                .filter(|z| z.anim.is_walking())
+                .map(move |z| distance(h, z))
+        })
+        .min_by(f32::total_cmp)
+    {
+        controller.stats.zombie_at(nearest);
     }
 
-    // draw zombies and herpooles
-    render::draw_herpooles(ctx, &h_ref, &h_ref.color());
-    zombies.iter().for_each(|z| {
-        render::draw_zombie(ctx, z, z.color());
-    });
+    for h in heroes.iter_mut().filter(|h| h.is_alive()) {
+        if zombies.iter().any(|z| zombies_reached(h, z)) {
+            log!("herpooles dead!");
+            h.anim.die(now);
+            h.anim.flash(now, CONTACT_FLASH);
+            controller
+                .stats
+                .died(h.player, Cause::Zombie, now, h.x, h.y);
+            events.push(Event::HerpoolesDied { x: h.x, y: h.y });
+        }
+    }
 
-    // move and draw poo
-    h_ref.poo.iter_mut().for_each(|p| {
+    // move poo, a fallen hero's poo flies on
+    let (min_x, min_y, max_x, max_y) = controller.world.poo_bounds();
+    heroes.iter_mut().flat_map(|h| &mut h.poo).for_each(|p| {
         move_poo(p);
-        if p.x < 2.0 || p.x > width as f32 - 3.0 || p.y < 2.0 || p.y > height as f32 - 3.0 {
+        if p.x < min_x || p.x > max_x || p.y < min_y || p.y > max_y {
             p.must_clean = true;
+            events.push(Event::PooSplat { x: p.x, y: p.y });
         }
-        render::draw_poo(ctx, p);
     });
 
     // check collision and mark for cleaning
     // zombies is a &mut
     for z in zombies.iter_mut() {
-        for p in &mut h_ref.poo {
-            if hit_zombie(&p, &z) {
-                p.must_clean = true;
-                z.walking = false;
-                let _promise = controller.zombie_kill_sound.play().unwrap();
-                // count score. Using a Cell because the inner value is only a number
-                let mut score = controller.score.get();
-                score += 1;
-                controller.score.set(score);
-                log!("score: {}", controller.score.get());
+        for h in heroes.iter_mut() {
+            for p in &mut h.poo {
+                if hit_zombie(p, z) {
+                    p.must_clean = true;
+                    z.anim.die(now);
+                    events.push(Event::ZombieKilled { x: z.x, y: z.y });
+                    h.score += 1;
+                    controller.score += 1;
+                    controller.stats.hit();
+                    log!("score: {}", controller.score);
+                }
             }
         }
     }
 
+    if controller.rules() == Rules::Versus {
+        hit_rivals(heroes, now);
+    }
+
     // clean poo
     // retain removes when predicate is false
-    h_ref.poo.retain(|&p| !p.must_clean);
+    heroes
+        .iter_mut()
+        .for_each(|h| h.poo.retain(|&p| !p.must_clean));
+    events
+}
+
+fn hit_hero(p: &Poo, h: &Herpooles, now: f64) -> bool {
+    let d = (p.x - h.x) * (p.x - h.x) + (p.y - h.y) * (p.y - h.y);
+    !p.must_clean && h.is_alive() && !h.is_stunned(now) && d < HIT_RADIUS * HIT_RADIUS
+}
 
-    // clean dirty zombies
-    zombies.retain(|z| z.walking);
-    if zombies.is_empty() {
-        log!("no zombies");
+// Versus: poo stuns the other heroes, the hit is the thrower's. Poo flies through a hero
+// who is stunned already.
+fn hit_rivals(heroes: &mut [Herpooles], now: f64) {
+    let mut hits = vec![];
+    for (thrower, h) in heroes.iter().enumerate() {
+        for (i, p) in h.poo.iter().enumerate() {
+            let rival = heroes
+                .iter()
+                .enumerate()
+                .position(|(j, r)| j != thrower && hit_hero(p, r, now));
+            if let Some(rival) = rival {
+                hits.push((thrower, i, rival));
+            }
+        }
+    }
+    for (thrower, i, rival) in hits {
+        heroes[thrower].poo[i].must_clean = true;
+        let r = &mut heroes[rival];
+        // hit by two poo on the same tick, only the first counts
+        if r.is_stunned(now) {
+            continue;
+        }
+        r.stunned_until = now + STUN;
+        r.anim.flash(now, STUN);
+        heroes[thrower].stuns += 1;
     }
 }
+
+// Draws the world as seen by the camera. Expects the canvas to be cleared and the camera
+// transform applied.
+pub fn draw(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    heroes: &[Herpooles],
+    controller: &Controller,
+    assets: &Assets,
+    skin: &Skin,
+    camera: &Camera,
+) {
+    let now = controller.time;
+    render::draw_world_bounds(ctx, &controller.world, &skin.theme.palette);
+    heroes
+        .iter()
+        .for_each(|h| render::draw_herpooles(ctx, skin, h, now));
+    // zombies wait outside the world, most of a big horde is off screen
+    controller
+        .zombies
+        .iter()
+        .filter(|z| camera.is_visible(geometry::Point { x: z.x, y: z.y }, 40.0))
+        .for_each(|z| {
+            render::draw_zombie(ctx, skin, z, now);
+        });
+    heroes.iter().flat_map(|h| &h.poo).for_each(|p| {
+        render::draw_poo(ctx, &assets.atlas, p);
+    });
+}
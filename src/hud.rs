@@ -0,0 +1,148 @@
+use wasm_bindgen::prelude::*;
+
+use crate::game::{Controller, Herpooles};
+use crate::viewport::VIEW_WIDTH;
+use crate::window;
+
+const HUD_HEIGHT: f64 = 36.0;
+
+// What the HUD shows. The reload bar is kept in tenths so it changes a few times per
+// shot rather than on every frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct HeroValues {
+    score: u32,
+    alive: bool,
+    reload_tenths: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HudValues {
+    score: u32,
+    level: u16,
+    zombies_left: usize,
+    heroes: Vec<HeroValues>,
+}
+
+impl HudValues {
+    pub fn new(heroes: &[Herpooles], controller: &Controller) -> HudValues {
+        HudValues {
+            score: controller.score,
+            level: controller.level(),
+            zombies_left: controller.zombies_left(),
+            heroes: heroes
+                .iter()
+                .map(|h| HeroValues {
+                    score: h.score,
+                    alive: h.is_alive(),
+                    reload_tenths: (h.reload_progress(controller.time) * 10.0) as u8,
+                })
+                .collect(),
+        }
+    }
+}
+
+// The HUD is drawn into its own canvas only when a value changes, every frame just
+// stamps that layer over the view.
+pub struct Hud {
+    layer: web_sys::HtmlCanvasElement,
+    layer_ctx: web_sys::CanvasRenderingContext2d,
+    shown: Option<HudValues>,
+}
+
+impl Hud {
+    pub fn new(document: &web_sys::Document) -> Hud {
+        let layer = document
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let layer_ctx = layer
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .unwrap();
+        let mut hud = Hud {
+            layer,
+            layer_ctx,
+            shown: None,
+        };
+        hud.fit();
+        hud
+    }
+
+    // As sharp as the main canvas on HiDPI screens, again whenever the window is resized
+    // since the device pixel ratio changes with the zoom and the screen. Resizing clears the
+    // layer, it is drawn again on the next update.
+    pub fn fit(&mut self) {
+        let ratio = window().device_pixel_ratio();
+        self.layer.set_width((VIEW_WIDTH * ratio) as u32);
+        self.layer.set_height((HUD_HEIGHT * ratio) as u32);
+        self.layer_ctx
+            .set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)
+            .unwrap();
+        self.shown = None;
+    }
+
+    pub fn update(&mut self, values: HudValues) {
+        if self.shown.as_ref() == Some(&values) {
+            return;
+        }
+        let ctx = &self.layer_ctx;
+        ctx.clear_rect(0.0, 0.0, VIEW_WIDTH, HUD_HEIGHT);
+        // readable over any theme
+        ctx.set_fill_style_str("rgba(255, 255, 255, 0.7)");
+        ctx.fill_rect(0.0, 0.0, VIEW_WIDTH, HUD_HEIGHT);
+        ctx.set_fill_style_str("black");
+        ctx.set_font("18px sans-serif");
+        ctx.set_text_align("left");
+        ctx.set_text_baseline("middle");
+        let text = format!(
+            "Score: {}   Level: {}   Zombies: {}",
+            values.score, values.level, values.zombies_left
+        );
+        ctx.fill_text(&text, 12.0, HUD_HEIGHT / 2.0).unwrap();
+
+        // the last hero on the right, each with its own score in co-op
+        let count = values.heroes.len();
+        for (i, hero) in values.heroes.iter().enumerate() {
+            let health_x = VIEW_WIDTH - 160.0 - 300.0 * (count - 1 - i) as f64;
+            if count > 1 {
+                ctx.set_fill_style_str("black");
+                let label = format!("P{}: {}", i + 1, hero.score);
+                ctx.fill_text(&label, health_x - 100.0, HUD_HEIGHT / 2.0)
+                    .unwrap();
+            }
+            Hud::draw_hero(ctx, hero, health_x);
+        }
+        self.shown = Some(values);
+    }
+
+    fn draw_hero(ctx: &web_sys::CanvasRenderingContext2d, hero: &HeroValues, health_x: f64) {
+        // health: one hit is fatal, so herpooles is either up or down
+        ctx.set_fill_style_str(if hero.alive { "red" } else { "lightgrey" });
+        ctx.fill_rect(health_x, 10.0, 14.0, 14.0);
+
+        // reload bar
+        let bar_x = health_x + 20.0;
+        ctx.set_stroke_style_str("black");
+        ctx.stroke_rect(bar_x, 12.0, 120.0, 10.0);
+        ctx.set_fill_style_str(if hero.reload_tenths >= 10 {
+            "saddlebrown"
+        } else {
+            "grey"
+        });
+        ctx.fill_rect(bar_x, 12.0, 12.0 * f64::from(hero.reload_tenths), 10.0);
+    }
+
+    pub fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        ctx.draw_image_with_html_canvas_element_and_dw_and_dh(
+            &self.layer,
+            0.0,
+            0.0,
+            VIEW_WIDTH,
+            HUD_HEIGHT,
+        )
+        .unwrap();
+    }
+}
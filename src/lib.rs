@@ -1,45 +1,77 @@
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::rc::Rc;
-mod geometry;
+pub mod geometry;
+pub mod net;
 mod render;
 #[macro_use]
 mod utils;
 use utils::set_panic_hook;
 
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 #[macro_use]
 mod callbacks;
-mod game;
+pub mod achievements;
+pub mod animation;
+mod assets;
+pub mod audio;
+pub mod camera;
+#[cfg(feature = "debug-overlay")]
+mod debug;
+mod figures;
+pub mod game;
+mod hud;
+mod netplay;
+mod online;
+pub mod particles;
+pub mod replay;
+pub mod rollback;
+pub mod scores;
+pub mod screen;
+mod settings;
+pub mod snapshot;
+pub mod spectate;
+mod spectating;
+pub mod stats;
+mod storage;
+pub mod theme;
+mod toast;
+pub mod versus;
+pub mod viewport;
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PressedKeys {
-    right: bool,
-    left: bool,
-    up: bool,
-    down: bool,
+    pub right: bool,
+    pub left: bool,
+    pub up: bool,
+    pub down: bool,
 }
 
+// The held movement keys of WASD and of the arrows, see callbacks::player_keys.
+pub type KeySets = [PressedKeys; game::MAX_PLAYERS];
+
+/// The main loop closure, shared with the loop itself so it can request the next frame.
+pub type MainLoop = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+// Longest frame the simulation catches up on, in seconds.
+const MAX_FRAME_TIME: f64 = 0.1;
+
 fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 // window.request_anination_frame -> Result<i32, JsValue>
-pub fn request_animation_frame(f: &Closure<dyn FnMut()>) -> i32 {
+pub fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) -> i32 {
     window()
         .request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK")
 }
-pub fn cancel_animation_frame(handle: i32) {
-    window()
-        .cancel_animation_frame(handle)
-        .expect("should register `cancelAnimationFrame` OK");
-}
 
 #[wasm_bindgen(start)]
-fn main() -> Result<(), JsValue> {
+async fn main() -> Result<(), JsValue> {
     set_panic_hook();
     let window = window();
-    // using the window here and then moving it into the main closure
+    // using the window here
     let document = window.document().unwrap();
     let canvas = document.get_element_by_id("canvas").unwrap();
     let htmlcanvas = canvas
@@ -52,71 +84,315 @@ fn main() -> Result<(), JsValue> {
         .dyn_into::<web_sys::CanvasRenderingContext2d>()
         .unwrap();
 
-    // gets moved in the main closure
-    let score_element = document
-        .get_element_by_id("score")
-        .expect("should have #score on the page");
-    let score_element = Rc::new(score_element);
-
-    // canvas boarder
-    let width = htmlcanvas.width() as f64;
-    let height = htmlcanvas.height() as f64;
-    ctx.stroke_rect(1.0, 1.0, width - 1.0, height - 1.0);
+    viewport::fit(&htmlcanvas, &ctx);
+    let hud = Rc::new(RefCell::new(hud::Hud::new(&document)));
+    callbacks::add_resize_event(&htmlcanvas, &ctx, &hud);
 
     // keyboard events
-    let pressed_keys = PressedKeys {
-        left: false,
-        right: false,
-        up: false,
-        down: false,
-    };
-    let pressed_keys = Rc::new(Cell::new(pressed_keys));
+    let pressed_keys = Rc::new(Cell::new(KeySets::default()));
     callbacks::add_key_events(&pressed_keys, &document);
+    let loop_pressed_keys = pressed_keys.clone();
 
-    let herpooles = Rc::new(RefCell::new(game::Herpooles::new()));
-    callbacks::add_shoot(&herpooles, &document);
+    let settings = settings::Settings::load();
+    let sounds = audio::SoundManager::new(&settings);
+    sounds.unlock_on_gesture(&document);
+    callbacks::add_settings_controls(&Rc::new(Cell::new(settings)), &sounds, &document);
 
-    // TODO: avoid hardcoded level 1, use the controller
-    let zombies = Box::new((0..10).map(|_| game::Zombie::new(1)).collect());
-    // TODO: avoid the controller owning the zombies
-    let mut controller = game::Controller::new(zombies);
+    // nothing starts until every asset is in
+    let assets = match assets::load(&ctx, &sounds).await {
+        Ok(assets) => assets,
+        Err(e) => {
+            render::draw_load_error(&ctx, &e.to_string());
+            return Err(e.into());
+        }
+    };
+
+    let params = web_sys::UrlSearchParams::new_with_str(&window.location().search()?)?;
+    online::configure(&params);
+    // ?theme=<url of a JSON theme> replaces the stored theme
+    let theme_url = params.get("theme");
+    let theme = match theme_url {
+        Some(url) => match assets::load_theme(&url).await {
+            Ok(theme) => {
+                theme.save();
+                theme
+            }
+            Err(e) => {
+                log!("could not load theme {}: {:?}", url, e);
+                theme::Theme::load()
+            }
+        },
+        None => theme::Theme::load(),
+    };
+    let skin = Rc::new(RefCell::new(figures::Skin::new(theme)));
+    callbacks::add_theme_select(&skin, &document);
+
+    // ?watch= shows a game played elsewhere, ?room= plays online, both straight into the game
+    let mut watching = spectating::watch(&params)?;
+    let mut netplay = match watching {
+        Some(_) => None,
+        None => netplay::connect(&params)?,
+    };
+    // ?broadcast= sends a game played here to its watchers
+    let mut broadcaster = match (&watching, &netplay) {
+        (None, None) => spectating::broadcast(&params)?,
+        _ => None,
+    };
+    let screen = Rc::new(RefCell::new(match (&watching, &netplay) {
+        (None, None) => screen::Screen::Title,
+        _ => screen::Screen::Playing,
+    }));
+    let loop_screen = screen.clone();
+    // in a room or watching, until quitting to the title leaves it
+    let online = Rc::new(Cell::new(watching.is_some() || netplay.is_some()));
+    let loop_online = online.clone();
+    let scores = Rc::new(RefCell::new(scores::HighScores::load()));
+    let loop_scores = scores.clone();
 
-    // animation_id is used in the first frame request.
-    let animation_id = Rc::new(Cell::new(0));
-    // moved in the main_loop_closure
-    let closed_animation_id = animation_id.clone();
+    let heroes = Rc::new(RefCell::new(game::Herpooles::team(1)));
+    callbacks::add_shoot(&screen, &heroes, &document);
+
+    // TODO: avoid the controller owning the zombies
+    let controller = Rc::new(RefCell::new(game::Controller::new(1, game::Rules::Coop)));
+    let loop_controller = controller.clone();
 
     // main game loop
     // create two Rc -- one is moved in the closure
-    let f = Rc::new(RefCell::new(None));
+    // The loop runs on every screen, only the Playing screen runs the simulation.
+    let f: MainLoop = Rc::new(RefCell::new(None));
     let g = f.clone();
-    let main_loop_closure = Closure::new(move || {
-        controller.check();
-        game::step(
-            &ctx,
-            // Need a reference because cannot move it out of its environment (closure is FnMut),
-            &herpooles,
-            &pressed_keys,
-            &mut controller,
-        );
-
-        callbacks::update_score(&controller.score, &score_element, &window);
-
-        if herpooles.borrow().is_alive() {
-            let id = request_animation_frame(g.borrow().as_ref().unwrap());
-            closed_animation_id.set(id);
+    let loop_ctx = ctx.clone();
+    let loop_heroes = heroes.clone();
+    // frame time not simulated yet, in seconds
+    let mut lag = 0.0;
+    let mut last_timestamp: Option<f64> = None;
+    // the screen of the last frame, to notice pausing and coming back to the title
+    let mut last_screen: Option<screen::Screen> = None;
+    // level of the run that can be continued from the title
+    let mut saved_level: Option<u16> = None;
+    let mut particles = particles::ParticleSystem::default();
+    let loop_hud = hud.clone();
+    let mut achievements = achievements::Achievements::load();
+    let mut toasts = toast::Toasts::default();
+    #[cfg(feature = "debug-overlay")]
+    let mut debug = debug::DebugOverlay::new(&document);
+    // only for runs played here, the servers start the others
+    #[cfg(feature = "debug-overlay")]
+    if let (None, None, Some(n)) = (&watching, &netplay, debug.horde()) {
+        controller.borrow_mut().crowd(n);
+    }
+    let mut camera = camera::Camera::new(viewport::VIEW_WIDTH as f32, viewport::VIEW_HEIGHT as f32);
+    let main_loop_closure = Closure::new(move |timestamp: f64| {
+        // requestAnimationFrame timestamps are in milliseconds. The first frame after the tab
+        // was hidden comes long after the last one, it must not be caught up on.
+        let elapsed = match last_timestamp.replace(timestamp) {
+            Some(last) => ((timestamp - last) / 1000.0).min(MAX_FRAME_TIME),
+            None => game::TICK,
+        };
+
+        #[cfg(feature = "debug-overlay")]
+        debug.frame(elapsed);
+
+        let mut screen = loop_screen.borrow_mut();
+        let mut controller = loop_controller.borrow_mut();
+        // Need a reference because cannot move it out of its environment (closure is FnMut),
+        let mut heroes = loop_heroes.borrow_mut();
+        if let Some(watcher) = &watching {
+            // somebody else plays the game, it is only shown
+            let level = controller.level();
+            let events = watcher
+                .borrow_mut()
+                .frame(elapsed, &mut heroes, &mut controller);
+            let listener = game::focus(&heroes);
+            for event in &events {
+                sounds.play_event(event, listener, &camera);
+                particles.handle(event);
+            }
+            particles.update(elapsed as f32);
+            if controller.level() > level {
+                screen.level_started(controller.level());
+            }
+            screen.update(elapsed);
+            camera.follow(listener, &controller.world, elapsed as f32);
+        } else if let Some(net) = &netplay {
+            // the room server plays the game, only the input of the hero goes out
+            let keys = if screen.is_playing() {
+                callbacks::player_keys(loop_pressed_keys.get(), 1)[0]
+            } else {
+                PressedKeys::default()
+            };
+            let events = net
+                .borrow_mut()
+                .frame(elapsed, keys, &mut heroes, &mut controller);
+            let me = &heroes[..heroes.len().min(1)];
+            let listener = game::focus(me);
+            for event in &events {
+                sounds.play_event(event, listener, &camera);
+                particles.handle(event);
+            }
+            particles.update(elapsed as f32);
+            screen.update(elapsed);
+            camera.follow(listener, &controller.world, elapsed as f32);
+        } else if screen.is_playing() {
+            lag += elapsed;
         } else {
-            let audio = web_sys::HtmlAudioElement::new_with_src("resources/zombie-hit.wav")
-                .expect("Could not load wav");
-            let _promise = audio.play().unwrap();
+            // nothing to catch up on when coming back to the game
+            lag = 0.0;
+            screen.update(elapsed);
+            // a new run starts wherever the camera was left
+            camera.follow(game::focus(&heroes), &controller.world, elapsed as f32);
+        }
+        #[cfg(feature = "debug-overlay")]
+        debug.begin_ticks();
+        let date = js_sys::Date::now();
+        while lag >= game::TICK {
+            let keys = callbacks::player_keys(loop_pressed_keys.get(), heroes.len());
+            let inputs: Vec<game::Input> = heroes
+                .iter()
+                .zip(keys)
+                .map(|(h, keys)| game::Input::capture(h, keys))
+                .collect();
+            for input in &inputs {
+                controller.replay.push(*input);
+            }
+            let level = controller.level();
+            let events = game::tick(&mut heroes, &inputs, &mut controller);
+            let listener = game::focus(&heroes);
+            for event in &events {
+                sounds.play_event(event, listener, &camera);
+                particles.handle(event);
+            }
+            let unlocked = achievements.update(&events, &controller.stats, controller.time, date);
+            for achievement in unlocked {
+                toasts.push(format!("Achievement unlocked: {}", achievement.title));
+            }
+            particles.update(game::TICK as f32);
+            camera.follow(listener, &controller.world, game::TICK as f32);
+            lag -= game::TICK;
+            #[cfg(feature = "debug-overlay")]
+            debug.tick_done();
+            if game::run_over(&heroes, &controller) {
+                snapshot::Snapshot::clear();
+                let scores = loop_scores.borrow();
+                if controller.rules() == game::Rules::Versus {
+                    // rivals make no high scores
+                    screen.game_over(controller.score);
+                } else if scores.qualifies(controller.score) {
+                    screen.enter_name(controller.score, &scores.last_name);
+                } else {
+                    // submitted after the name entry otherwise
+                    online::submit(&scores.last_name, &controller);
+                    screen.game_over(controller.score);
+                }
+                break;
+            }
+            if controller.level() > level {
+                screen.level_started(controller.level());
+                snapshot::Snapshot::take(&heroes, &controller).save();
+                break;
+            }
+        }
+        #[cfg(feature = "debug-overlay")]
+        debug.end_ticks();
+        // the title shows whatever run was last, it is not played
+        if let Some(broadcaster) = broadcaster
+            .as_mut()
+            .filter(|_| *screen != screen::Screen::Title)
+        {
+            broadcaster.send(&controller.replay);
+        }
+        achievements.save_if_changed();
+        toasts.update(elapsed);
+
+        // autosave on pause, whatever paused the game
+        if last_screen.as_ref() != Some(&*screen) {
+            match &*screen {
+                screen::Screen::Paused { .. } if netplay.is_none() && watching.is_none() => {
+                    snapshot::Snapshot::take(&heroes, &controller).save()
+                }
+                screen::Screen::Title => {
+                    // quitting an online game leaves the room
+                    if let Some(net) = netplay.take() {
+                        net.borrow().close();
+                    }
+                    if let Some(watcher) = watching.take() {
+                        watcher.borrow().close();
+                    }
+                    loop_online.set(false);
+                    saved_level = snapshot::Snapshot::load().map(|s| s.level())
+                }
+                _ => {}
+            }
+            last_screen = Some(screen.clone());
         }
+
+        if *screen == screen::Screen::Title {
+            render::draw_title(&loop_ctx, &loop_scores.borrow(), &achievements, saved_level);
+        } else {
+            #[cfg(feature = "debug-overlay")]
+            debug.begin_draw();
+            let skin = skin.borrow();
+            render::draw_background(&loop_ctx, &skin.theme.palette);
+            loop_ctx.save();
+            camera.apply(&loop_ctx);
+            game::draw(&loop_ctx, &heroes, &controller, &assets, &skin, &camera);
+            render::draw_particles(&loop_ctx, &particles);
+            #[cfg(feature = "debug-overlay")]
+            debug.draw_world(&loop_ctx, &heroes, &controller);
+            loop_ctx.restore();
+            #[cfg(feature = "debug-overlay")]
+            debug.end_draw();
+            // online, only the own hero has a block
+            let shown = match netplay {
+                Some(_) => heroes.len().min(1),
+                None => heroes.len(),
+            };
+            let mut hud = loop_hud.borrow_mut();
+            hud.update(hud::HudValues::new(&heroes[..shown], &controller));
+            hud.draw(&loop_ctx);
+            #[cfg(feature = "debug-overlay")]
+            debug.draw_stats(
+                &loop_ctx,
+                &heroes,
+                &controller,
+                particles.particles().count(),
+            );
+        }
+        match &*screen {
+            screen::Screen::LevelIntro { level, remaining } => {
+                render::draw_level_intro(&loop_ctx, *level, *remaining)
+            }
+            screen::Screen::Paused { .. } => render::draw_paused(&loop_ctx),
+            screen::Screen::EnterName { score, name } => {
+                render::draw_name_entry(&loop_ctx, *score, name)
+            }
+            screen::Screen::GameOver { score, rank } => render::draw_game_over(
+                &loop_ctx,
+                *score,
+                &heroes,
+                &controller,
+                &loop_scores.borrow(),
+                *rank,
+            ),
+            screen::Screen::Title | screen::Screen::Playing => {}
+        }
+        if let Some(status) = netplay.as_ref().and_then(|net| net.borrow().status()) {
+            render::draw_online_status(&loop_ctx, &status);
+        }
+        if let Some(status) = watching.as_ref().and_then(|w| w.borrow().status()) {
+            render::draw_watching_status(&loop_ctx, &status);
+        }
+        toasts.draw(&loop_ctx);
+
+        request_animation_frame(g.borrow().as_ref().unwrap());
     });
     // store the closure in the Rc
     *f.borrow_mut() = Some(main_loop_closure);
     // request the first frame
-    animation_id.set(request_animation_frame(f.borrow().as_ref().unwrap()));
+    request_animation_frame(f.borrow().as_ref().unwrap());
 
-    callbacks::add_play_pause_control(animation_id, f, &document);
-    callbacks::add_restart_event(&document);
+    callbacks::add_screen_controls(&screen, &heroes, &controller, &scores, &online, &document);
+    callbacks::add_auto_pause(&screen, &pressed_keys, &document);
+    callbacks::add_stats_export(&controller, &document);
     Ok(())
 }
@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{self, Controller, Event, Herpooles, Input};
+use crate::rollback::InputMessage;
+use crate::spectate::Ticks;
+
+// Online co-op: the room server of the server crate runs the game, the clients send the
+// inputs of their hero and draw the states it sends back. In rollback rooms the server only
+// passes the inputs around, every client plays the game itself, see rollback. Messages are JSON text frames,
+// externally tagged like {"welcome":{"player":1}}: the generator in the states cannot be
+// read back through an internally tagged enum.
+
+// Players of a room, it starts once MIN_ROOM_PLAYERS are in.
+pub const MIN_ROOM_PLAYERS: usize = 2;
+pub const MAX_ROOM_PLAYERS: usize = 4;
+// Ticks between two states from the server.
+pub const STATE_INTERVAL: u64 = 3;
+// Inputs a client keeps until the server acknowledges them, older ones are given up on.
+const MAX_PENDING: usize = 120;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientMessage {
+    Join { room: String },
+    // The input of the hero for one tick of the client, numbered from 1.
+    Input { seq: u64, input: Input },
+    // A rollback room for that many players, the first to join picks it.
+    JoinRollback { room: String, players: usize },
+    // Passed on to the others of the rollback room.
+    Inputs(InputMessage),
+    // Sends a game played alone to whoever watches the room, see spectate.
+    Broadcast { room: String },
+    // The broadcaster's run as it goes on.
+    Ticks(Ticks),
+    // Shows the game broadcast in the room, read-only.
+    Watch { room: String },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServerMessage {
+    Welcome { player: usize },
+    Refused { reason: String },
+    State(Box<State>),
+    // Every player of a rollback room is in, the game starts from the seed.
+    Start { seed: u64, players: usize },
+    Inputs(InputMessage),
+    Left { player: usize },
+    // The broadcaster's run so far to a new watcher, then as it goes on.
+    Ticks(Ticks),
+    // The broadcaster went away, another one may come.
+    Stopped,
+}
+
+// The room as one of its players gets it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct State {
+    // ticks of the room since it was opened
+    pub tick: u64,
+    // the last input of the receiving player the server played
+    pub ack: u64,
+    // no run until enough players are in
+    pub waiting: bool,
+    pub heroes: Vec<Herpooles>,
+    pub controller: Controller,
+    // what happened since the previous state, for the sounds and effects
+    pub events: Vec<Event>,
+}
+
+impl ClientMessage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("serialize")
+    }
+}
+
+impl ServerMessage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("serialize")
+    }
+}
+
+// The client side of a room, without the socket. The other heroes and the zombies are
+// shown between the last two states, so they move smoothly although states come a few
+// ticks apart. The own hero is predicted: the inputs the server has not played yet are
+// played again on top of the last state, so it answers the keys at once.
+pub struct Session {
+    pub player: usize,
+    seq: u64,
+    pending: VecDeque<(u64, Input)>,
+    previous: Option<State>,
+    latest: Option<State>,
+    // seconds since the latest state came in
+    since: f64,
+    // of the states received since the last frame
+    events: Vec<Event>,
+}
+
+impl Session {
+    pub fn new(player: usize) -> Session {
+        Session {
+            player,
+            seq: 0,
+            pending: VecDeque::new(),
+            previous: None,
+            latest: None,
+            since: 0.0,
+            events: vec![],
+        }
+    }
+
+    // Keeps the input of one tick for the prediction and returns the message that sends it.
+    pub fn input(&mut self, input: Input) -> ClientMessage {
+        self.seq += 1;
+        self.pending.push_back((self.seq, input));
+        if self.pending.len() > MAX_PENDING {
+            self.pending.pop_front();
+        }
+        ClientMessage::Input {
+            seq: self.seq,
+            input,
+        }
+    }
+
+    pub fn receive(&mut self, state: State) {
+        self.pending.retain(|(seq, _)| *seq > state.ack);
+        self.events.extend_from_slice(&state.events);
+        self.previous = self.latest.replace(state);
+        self.since = 0.0;
+    }
+
+    pub fn advance(&mut self, dt: f64) {
+        self.since += dt;
+    }
+
+    // The events that came with the states since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.latest.as_ref().is_none_or(|s| s.waiting)
+    }
+
+    // Inputs sent but not played by the server yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    // The heroes and the world to draw, the own hero first. None until a state came in.
+    pub fn frame(&self) -> Option<(Vec<Herpooles>, Controller)> {
+        let latest = self.latest.as_ref()?;
+        let mut heroes = latest.heroes.clone();
+        let mut controller = latest.controller.clone();
+        if let Some(previous) = &self.previous {
+            let t = self.since / (STATE_INTERVAL as f64 * game::TICK);
+            interpolate(previous, &mut heroes, &mut controller, t.min(1.0) as f32);
+        }
+        if let Some(me) = latest.heroes.iter().find(|h| h.player == self.player) {
+            let mut me = me.clone();
+            for (_, input) in &self.pending {
+                input.apply(&mut me);
+                game::move_herpooles(&mut me, &input.keys, &controller.world);
+            }
+            heroes.retain(|h| h.player != self.player);
+            heroes.insert(0, me);
+        }
+        Some((heroes, controller))
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+// Moves what is in both states from where it was in previous to where it is now, t of the
+// way. Zombies and poo are matched by order, so they only move while none came or went.
+pub fn interpolate(
+    previous: &State,
+    heroes: &mut [Herpooles],
+    controller: &mut Controller,
+    t: f32,
+) {
+    for h in heroes.iter_mut() {
+        let Some(before) = previous.heroes.iter().find(|b| b.player == h.player) else {
+            continue;
+        };
+        let (x, y) = (h.x, h.y);
+        h.x = lerp(before.x, x, t);
+        h.y = lerp(before.y, y, t);
+        if before.poo().len() == h.poo().len() {
+            for (p, b) in h.poo_mut().iter_mut().zip(before.poo()) {
+                p.x = lerp(b.x, p.x, t);
+                p.y = lerp(b.y, p.y, t);
+            }
+        }
+    }
+    let before = &previous.controller;
+    if before.zombies().len() == controller.zombies().len() {
+        for (z, b) in controller.zombies_mut().iter_mut().zip(before.zombies()) {
+            z.x = lerp(b.x, z.x, t);
+            z.y = lerp(b.y, z.y, t);
+        }
+    }
+    controller.time = before.time + (controller.time - before.time) * f64::from(t);
+}
@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::game::{self, Controller, Event, Herpooles, Input};
+use crate::net::{ClientMessage, ServerMessage, Session};
+use crate::rollback::Rollback;
+use crate::PressedKeys;
+
+// The room server of the server crate, see herpooles-rooms.
+pub(crate) const DEFAULT_SERVER: &str = "ws://127.0.0.1:8788";
+
+// How the game is played once it runs.
+enum Play {
+    // the room server plays it and sends the states
+    Stream(Box<Session>),
+    // every client plays it, the inputs go around
+    Rollback(Box<Rollback>),
+}
+
+// An online game: this sends the input of the hero every tick and puts the game as it
+// should look now in the main loop's heroes and controller.
+pub struct NetPlay {
+    socket: web_sys::WebSocket,
+    server: String,
+    // rollback play for that many players, or the server plays
+    rollback: Option<usize>,
+    // from the welcome on
+    player: Option<usize>,
+    play: Option<Play>,
+    // why the server would not have us, or that it went away
+    problem: Option<String>,
+    // frame time not sent as input yet, in seconds
+    lag: f64,
+}
+
+// ?room=<name> plays online in that room, on the server of ?server=<ws url>. With
+// ?rollback=<players> every client plays the game itself, it starts once they are all in.
+pub fn connect(params: &web_sys::UrlSearchParams) -> Result<Option<Rc<RefCell<NetPlay>>>, JsValue> {
+    let Some(room) = params.get("room") else {
+        return Ok(None);
+    };
+    let server = params
+        .get("server")
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    let rollback = params.get("rollback").and_then(|p| p.parse().ok());
+    let socket = web_sys::WebSocket::new(&server)?;
+    let net = Rc::new(RefCell::new(NetPlay {
+        socket: socket.clone(),
+        server,
+        rollback,
+        player: None,
+        play: None,
+        problem: None,
+        lag: 0.0,
+    }));
+
+    let open_socket = socket.clone();
+    let open_closure = Closure::wrap(Box::new(move || {
+        let room = room.clone();
+        let join = match rollback {
+            Some(players) => ClientMessage::JoinRollback { room, players },
+            None => ClientMessage::Join { room },
+        };
+        if let Err(e) = open_socket.send_with_str(&join.to_json()) {
+            log!("could not join: {:?}", e);
+        }
+    }) as Box<dyn Fn()>);
+    socket.set_onopen(Some(open_closure.as_ref().unchecked_ref()));
+    open_closure.forget();
+
+    let message_net = net.clone();
+    let message_closure = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        let Some(text) = event.data().as_string() else {
+            return;
+        };
+        match serde_json::from_str(&text) {
+            Ok(message) => message_net.borrow_mut().receive(message),
+            Err(e) => log!("bad message from the room server: {}", e),
+        }
+    }) as Box<dyn Fn(_)>);
+    socket.set_onmessage(Some(message_closure.as_ref().unchecked_ref()));
+    message_closure.forget();
+
+    let close_net = net.clone();
+    let close_closure = Closure::wrap(Box::new(move || {
+        let mut net = close_net.borrow_mut();
+        if net.problem.is_none() {
+            net.problem = Some(format!("lost the connection to {}", net.server));
+        }
+    }) as Box<dyn Fn()>);
+    socket.set_onclose(Some(close_closure.as_ref().unchecked_ref()));
+    close_closure.forget();
+    Ok(Some(net))
+}
+
+impl NetPlay {
+    fn receive(&mut self, message: ServerMessage) {
+        match (message, &mut self.play) {
+            (ServerMessage::Welcome { player }, _) => {
+                self.player = Some(player);
+                if self.rollback.is_none() {
+                    self.play = Some(Play::Stream(Box::new(Session::new(player))));
+                }
+            }
+            (ServerMessage::Refused { reason }, _) => self.problem = Some(reason),
+            (ServerMessage::State(state), Some(Play::Stream(session))) => session.receive(*state),
+            (ServerMessage::Start { seed, players }, None) => {
+                if let Some(player) = self.player {
+                    match Rollback::new(seed, players, player) {
+                        Some(rollback) => self.play = Some(Play::Rollback(Box::new(rollback))),
+                        None => {
+                            self.problem =
+                                Some(format!("player {} in a game for {}", player + 1, players))
+                        }
+                    }
+                }
+            }
+            (ServerMessage::Inputs(inputs), Some(Play::Rollback(rollback))) => {
+                rollback.receive(&inputs)
+            }
+            (ServerMessage::Left { player }, _) => {
+                self.problem = Some(format!("player {} left the game", player + 1))
+            }
+            _ => {}
+        }
+    }
+
+    fn send(&self, message: &ClientMessage) {
+        if let Err(e) = self.socket.send_with_str(&message.to_json()) {
+            log!("could not send the input: {:?}", e);
+        }
+    }
+
+    // Sends one input per tick of the frame and puts the game as it should look now in
+    // heroes and controller, the own hero first. Returns the events of the ticks played.
+    pub fn frame(
+        &mut self,
+        elapsed: f64,
+        keys: PressedKeys,
+        heroes: &mut Vec<Herpooles>,
+        controller: &mut Controller,
+    ) -> Vec<Event> {
+        let mut events = vec![];
+        let Some(me) = heroes.first_mut() else {
+            return events;
+        };
+        self.lag += elapsed;
+        while self.lag >= game::TICK {
+            let input = Input::capture(me, keys);
+            let message = match self.play.as_mut() {
+                None => {
+                    self.lag = 0.0;
+                    break;
+                }
+                Some(Play::Stream(session)) => session.input(input),
+                Some(Play::Rollback(rollback)) => {
+                    let over = game::all_gone(&rollback.frame().heroes);
+                    let Some(played) = (!over).then(|| rollback.advance(input)).flatten() else {
+                        // the others are behind, the input waits for them
+                        self.lag = self.lag.min(game::TICK);
+                        break;
+                    };
+                    events.extend(played);
+                    ClientMessage::Inputs(rollback.message())
+                }
+            };
+            self.send(&message);
+            // fire is asked for once
+            Input {
+                fire: false,
+                ..input
+            }
+            .apply(me);
+            self.lag -= game::TICK;
+        }
+        let now = match self.play.as_mut() {
+            None => return events,
+            Some(Play::Stream(session)) => {
+                session.advance(elapsed);
+                events.extend(session.take_events());
+                session.frame()
+            }
+            Some(Play::Rollback(rollback)) => {
+                let frame = rollback.frame();
+                let mut room_heroes = frame.heroes.clone();
+                room_heroes.rotate_left(rollback.player);
+                Some((room_heroes, frame.controller.clone()))
+            }
+        };
+        if let Some((room_heroes, room_controller)) = now {
+            // fire asked for since the last tick goes out with the next one
+            let fire = heroes
+                .first()
+                .is_some_and(|me| Input::capture(me, keys).fire);
+            *heroes = room_heroes;
+            *controller = room_controller;
+            if let Some(me) = heroes.first_mut().filter(|_| fire) {
+                me.fire_poo();
+            }
+        }
+        events
+    }
+
+    // What to show over the game until it runs, or once it cannot go on.
+    pub fn status(&self) -> Option<String> {
+        if let Some(problem) = &self.problem {
+            return Some(problem.clone());
+        }
+        match (&self.player, &self.play) {
+            (None, _) => Some(format!("Connecting to {}", self.server)),
+            (Some(_), None) => Some("Waiting for players".to_string()),
+            (_, Some(Play::Stream(session))) if session.is_waiting() => {
+                Some("Waiting for players".to_string())
+            }
+            (_, Some(Play::Rollback(rollback))) if game::all_gone(&rollback.frame().heroes) => {
+                Some(format!(
+                    "The run is over, score {}",
+                    rollback.frame().controller.score
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    // Leaves the room, the hero goes with it.
+    pub fn close(&self) {
+        let _ = self.socket.close();
+    }
+}
@@ -0,0 +1,71 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::game::Controller;
+use crate::replay::{Mode, Submission};
+use crate::storage;
+use crate::window;
+
+// The scoreboard server finished runs are sent to, see the server crate. Off unless set.
+const STORAGE_KEY: &str = "herpooles.scoreboard";
+
+// ?scoreboard=<url> sets the server, like http://localhost:8787, and it is remembered.
+// An empty ?scoreboard= stops submitting.
+pub fn configure(params: &web_sys::UrlSearchParams) {
+    if let Some(url) = params.get("scoreboard") {
+        let url = url.trim_end_matches('/').to_string();
+        storage::save(STORAGE_KEY, &Some(url).filter(|u| !u.is_empty()));
+    }
+}
+
+fn endpoint() -> Option<String> {
+    storage::load::<Option<String>>(STORAGE_KEY).flatten()
+}
+
+// Sends the run that just ended in the background. The server plays the replay again, the
+// local high scores do not wait for it.
+pub fn submit(name: &str, controller: &Controller) {
+    let Some(endpoint) = endpoint() else {
+        return;
+    };
+    let Some(mode) = Mode::for_players(controller.replay.players) else {
+        return;
+    };
+    let submission = Submission {
+        mode,
+        name: name.to_string(),
+        score: controller.score,
+        level: controller.level(),
+        replay: controller.replay.clone(),
+    };
+    let body = serde_json::to_string(&submission).expect("serialize");
+    spawn_local(async move {
+        match post(&format!("{}/api/scores", endpoint), &body).await {
+            Ok(reply) => log!("scoreboard: {}", reply),
+            Err(e) => log!("could not submit the score: {:?}", e),
+        }
+    });
+}
+
+async fn post(url: &str, body: &str) -> Result<String, JsValue> {
+    let headers = web_sys::Headers::new()?;
+    headers.set("Content-Type", "application/json")?;
+    let init = web_sys::RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&JsValue::from_str(body));
+    let response: web_sys::Response = JsFuture::from(window().fetch_with_str_and_init(url, &init))
+        .await?
+        .dyn_into()?;
+    let text = JsFuture::from(response.text()?).await?;
+    let text = text.as_string().unwrap_or_default();
+    if response.ok() {
+        Ok(text)
+    } else {
+        Err(JsValue::from_str(&format!(
+            "status {}: {}",
+            response.status(),
+            text
+        )))
+    }
+}
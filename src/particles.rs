@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use crate::game::Event;
+
+// Upper bound on live particles. When the pool is full the oldest ones are recycled,
+// so late levels with hundreds of zombies dying at once stay fast.
+pub const MAX_PARTICLES: usize = 512;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    vx: f32,
+    vy: f32,
+    gravity: f32,
+    age: f32,
+    life: f32,
+    pub size: f32,
+    pub color: &'static str,
+}
+
+impl Particle {
+    fn is_alive(&self) -> bool {
+        self.age < self.life
+    }
+
+    // From 1 when emitted down to 0 at the end of its life.
+    pub fn alpha(&self) -> f32 {
+        (1.0 - self.age / self.life).clamp(0.0, 1.0)
+    }
+}
+
+// How a burst of particles looks. Speeds in px/s, gravity in px/s², life in seconds.
+pub struct Emitter {
+    count: usize,
+    speed: (f32, f32),
+    // direction of the burst and how wide it opens, in radians
+    angle: f32,
+    spread: f32,
+    gravity: f32,
+    life: (f32, f32),
+    size: f32,
+    color: &'static str,
+}
+
+// brown bits flying off where poo lands
+pub const SPLAT: Emitter = Emitter {
+    count: 10,
+    speed: (40.0, 120.0),
+    angle: -PI / 2.0,
+    spread: PI,
+    gravity: 300.0,
+    life: (0.3, 0.6),
+    size: 3.0,
+    color: "saddlebrown",
+};
+
+pub const ZOMBIE_DEATH: Emitter = Emitter {
+    count: 16,
+    speed: (20.0, 80.0),
+    angle: 0.0,
+    spread: 2.0 * PI,
+    gravity: 120.0,
+    life: (0.4, 0.9),
+    size: 2.0,
+    color: "grey",
+};
+
+pub const HERPOOLES_DAMAGE: Emitter = Emitter {
+    count: 20,
+    speed: (60.0, 160.0),
+    angle: 0.0,
+    spread: 2.0 * PI,
+    gravity: 0.0,
+    life: (0.2, 0.5),
+    size: 2.0,
+    color: "red",
+};
+
+fn between((min, max): (f32, f32)) -> f32 {
+    min + rand::random::<f32>() * (max - min)
+}
+
+// Purely cosmetic: it only reads game events and never touches the simulation.
+pub struct ParticleSystem {
+    // in emission order, the oldest at the front
+    pool: VecDeque<Particle>,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        ParticleSystem {
+            pool: VecDeque::with_capacity(MAX_PARTICLES),
+        }
+    }
+}
+
+impl ParticleSystem {
+    pub fn emit(&mut self, emitter: &Emitter, x: f32, y: f32) {
+        for _ in 0..emitter.count {
+            let angle = emitter.angle + (rand::random::<f32>() - 0.5) * emitter.spread;
+            let speed = between(emitter.speed);
+            let particle = Particle {
+                x,
+                y,
+                vx: angle.cos() * speed,
+                vy: angle.sin() * speed,
+                gravity: emitter.gravity,
+                age: 0.0,
+                life: between(emitter.life),
+                size: emitter.size,
+                color: emitter.color,
+            };
+            if self.pool.len() == MAX_PARTICLES {
+                self.pool.pop_front();
+            }
+            self.pool.push_back(particle);
+        }
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::ZombieKilled { x, y } => {
+                self.emit(&SPLAT, x, y);
+                self.emit(&ZOMBIE_DEATH, x, y);
+            }
+            Event::PooSplat { x, y } => self.emit(&SPLAT, x, y),
+            Event::HerpoolesDied { x, y } => self.emit(&HERPOOLES_DAMAGE, x, y),
+            Event::ZombieEntered { .. } => {}
+        }
+    }
+
+    // dt in seconds
+    pub fn update(&mut self, dt: f32) {
+        for p in self.pool.iter_mut() {
+            p.vy += p.gravity * dt;
+            p.x += p.vx * dt;
+            p.y += p.vy * dt;
+            p.age += dt;
+        }
+        self.pool.retain(Particle::is_alive);
+    }
+
+    pub fn particles(&self) -> impl Iterator<Item = &Particle> {
+        self.pool.iter()
+    }
+}
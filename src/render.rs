@@ -1,245 +1,470 @@
-use wasm_bindgen::prelude::*;
-
-use crate::game::Direction;
+use crate::achievements::{Achievements, ACHIEVEMENTS};
+use crate::animation::Animation;
+use crate::assets::Atlas;
+use crate::figures::Skin;
+use crate::game::Controller;
 use crate::game::Herpooles;
 use crate::game::Poo;
+use crate::game::Rules;
+use crate::game::World;
 use crate::game::Zombie;
+use crate::particles::ParticleSystem;
+use crate::scores::HighScores;
+use crate::stats::Stats;
+use crate::theme::Palette;
+use crate::versus::{self, Outcome};
+use crate::viewport::{VIEW_HEIGHT, VIEW_WIDTH};
+
+// Dying characters fall over around their feet and fade out. Must be paired with a
+// ctx.restore() once the figure is drawn.
+fn begin_death_transform(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    anim: &Animation,
+    now: f64,
+    feet_x: f64,
+    feet_y: f64,
+) {
+    ctx.save();
+    let progress = anim.death_progress(now) as f64;
+    if progress > 0.0 {
+        ctx.translate(feet_x, feet_y).unwrap();
+        ctx.rotate(progress * std::f64::consts::FRAC_PI_2).unwrap();
+        ctx.translate(-feet_x, -feet_y).unwrap();
+        ctx.set_global_alpha(1.0 - progress);
+    }
+}
 
-// TODO: draw once in main and save it as a Path2D object.
-pub fn draw_herpooles(ctx: &web_sys::CanvasRenderingContext2d, h: &Herpooles, c: &str) {
-    ctx.set_stroke_style(&JsValue::from_str(c));
+pub fn draw_herpooles(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    skin: &Skin,
+    h: &Herpooles,
+    now: f64,
+) {
+    let (palette, figures) = (&skin.theme.palette, &skin.figures);
+    let color = if h.anim.flashing(now) {
+        &palette.herpooles_flash
+    } else if h.is_alive() {
+        palette.player(h.player)
+    } else {
+        &palette.herpooles_dead
+    };
     let hx: f64 = h.x.into();
     let hy: f64 = h.y.into();
-    // Set the starting position for drawing the human figure
-    let start_x = hx; //rect_width / 2.0;
-    let start_y = hy; //rect_height / 6.0;
-    const SCALE: f64 = 0.2;
-    // Set the sizes for different body parts
-    let head_radius = 20.0 * SCALE;
-    let body_height = 100.0 * SCALE;
-    let leg_height = 100.0 * SCALE;
-    let arm_width = 80.0 * SCALE;
-    let elbow_relative_point = 0.5; // ratio
-    let neck_height = body_height * 0.2;
-    // Set the color and line width for the path
-    //ctx.line_width = 2;
-    // Begin drawing the path
-    ctx.begin_path();
-    // Draw the head
-    ctx.move_to(start_x, start_y + head_radius);
-    ctx.line_to(start_x - head_radius, start_y + head_radius / 2.0);
-    ctx.line_to(start_x - head_radius, start_y - head_radius);
-    ctx.line_to(start_x + head_radius, start_y - head_radius);
-    ctx.line_to(start_x + head_radius, start_y + head_radius / 2.0);
-    ctx.line_to(start_x, start_y + head_radius);
-    // Draw the body
-    ctx.move_to(start_x, start_y + head_radius);
-    ctx.line_to(start_x, start_y + head_radius + body_height);
-    // Draw the legs
-    ctx.move_to(
-        start_x - arm_width / 4.0,
-        start_y + head_radius + body_height,
-    );
-    ctx.line_to(
-        start_x - arm_width / 4.0,
-        start_y + head_radius + body_height + leg_height,
-    );
-    ctx.move_to(
-        start_x + arm_width / 4.0,
-        start_y + head_radius + body_height,
-    );
-    ctx.line_to(
-        start_x + arm_width / 4.0,
-        start_y + head_radius + body_height + leg_height,
-    );
-    // Draw the arms until the elbow
-    ctx.move_to(start_x, start_y + head_radius + neck_height);
-    ctx.line_to(
-        start_x - arm_width / 2.0,           // elbow
-        start_y + head_radius + neck_height, // this is the height of the shoulders
-    );
-    ctx.move_to(start_x, start_y + head_radius + neck_height);
-    ctx.line_to(
-        start_x + arm_width / 2.0,
-        start_y + head_radius + neck_height,
-    );
-    // Draw the hands
-    let (left_end_x, right_end_x, left_end_y, right_end_y) = match h.bearing {
-        Direction::East => (
-            start_x + arm_width * elbow_relative_point,
-            start_x + arm_width,
-            start_y + head_radius + body_height * 0.1, // TODO: parameterize the tilt of the hands
-            start_y + head_radius + body_height * 0.1,
-        ),
-        Direction::West => (
-            start_x - arm_width, // until the end of the hand
-            start_x - arm_width * elbow_relative_point,
-            start_y + head_radius + body_height * 0.1,
-            start_y + head_radius + body_height * 0.1,
-        ),
-        Direction::South => (
-            start_x - arm_width * elbow_relative_point - 3.5, // until the elbow + something extra to not overlap his cap
-            start_x + arm_width * elbow_relative_point + 3.5,
-            start_y + head_radius + body_height * 0.5,
-            start_y + head_radius + body_height * 0.5,
-        ),
-        Direction::North => (
-            start_x - arm_width * elbow_relative_point, // until the elbow
-            start_x + arm_width * elbow_relative_point,
-            start_y + head_radius - body_height * 0.1,
-            start_y + head_radius - body_height * 0.1,
-        ),
+    begin_death_transform(ctx, &h.anim, now, hx, hy + figures.herpooles_feet);
+    ctx.translate(hx, hy).unwrap();
+    ctx.set_line_width(skin.theme.line_width);
+    ctx.set_stroke_style_str(color);
+    ctx.stroke_with_path(figures.herpooles_body(h.bearing, &h.anim));
+    ctx.set_fill_style_str(&palette.crown);
+    ctx.fill_with_path_2d(figures.crown());
+    ctx.set_fill_style_str(&palette.cape);
+    ctx.fill_with_path_2d(figures.cape());
+    ctx.restore();
+}
+
+pub fn draw_zombie(ctx: &web_sys::CanvasRenderingContext2d, skin: &Skin, z: &Zombie, now: f64) {
+    let (palette, figures) = (&skin.theme.palette, &skin.figures);
+    let color = if z.anim.is_walking() {
+        &palette.zombie
+    } else {
+        &palette.zombie_dying
     };
-    // left hand -- elbow is at start_x - arm_width * elbow_relative_point
-    ctx.move_to(
-        start_x - arm_width * elbow_relative_point,
-        start_y + head_radius + neck_height,
-    );
-    ctx.line_to(left_end_x, left_end_y);
-    // right hand
-    ctx.move_to(
-        start_x + arm_width * elbow_relative_point,
-        start_y + head_radius + neck_height,
-    );
-    ctx.line_to(right_end_x, right_end_y);
-    ctx.close_path();
-    ctx.stroke();
-    // Draw the crown
-    ctx.begin_path();
-    ctx.set_fill_style(&JsValue::from_str("brown"));
-    let crown_height = 1.0 * SCALE;
-    let crown_width = 50.0 * SCALE;
-    let half_base = crown_width / 6.0;
-    let crown_bottom = start_y - head_radius - crown_height;
-    let crown_top = crown_bottom - crown_width / 2.0;
-    let extra_height = crown_width * 0.1;
-    let crown_start_x = start_x - crown_width / 2.0;
-    // Left triangle of the crown
-    ctx.move_to(crown_start_x, crown_bottom);
-    ctx.line_to(crown_start_x + half_base, crown_top);
-    ctx.line_to(crown_start_x + 2.0 * half_base, crown_bottom);
-    ctx.line_to(crown_start_x, crown_bottom);
-    // Middle triangle
-    ctx.move_to(crown_start_x + 2.0 * half_base, crown_bottom);
-    ctx.line_to(crown_start_x + 3.0 * half_base, crown_top - extra_height);
-    ctx.line_to(crown_start_x + 4.0 * half_base, crown_bottom);
-    ctx.line_to(crown_start_x + 2.0 * half_base, crown_bottom);
-    // Right triangle
-    ctx.move_to(crown_start_x + 4.0 * half_base, crown_bottom);
-    ctx.line_to(crown_start_x + 5.0 * half_base, crown_top);
-    ctx.line_to(crown_start_x + 6.0 * half_base, crown_bottom);
-    ctx.line_to(crown_start_x + 4.0 * half_base, crown_bottom);
-
-    ctx.close_path();
-    ctx.fill();
-
-    // Draw the cape
-    ctx.begin_path();
-    ctx.set_fill_style(&JsValue::from_str("black"));
-    // elbow is at start_x + arm_width * elbow_relative_point
-    let cape_width = arm_width; // elbow to albow
-    let cape_height = leg_height + body_height;
-    let cape_start_x = start_x - cape_width / 2.0;
-    let cape_end_x = cape_start_x + cape_width;
-    let cape_top_y = start_y + head_radius + neck_height; // start at the shoulders
-    let cape_bottom_y = cape_top_y + body_height;
-    let left_tilt = cape_width * 0.1;
-
-    ctx.move_to(cape_start_x, cape_top_y);
-    ctx.line_to(cape_start_x - left_tilt, cape_bottom_y);
-    ctx.line_to(cape_end_x - left_tilt, cape_bottom_y);
-    ctx.line_to(cape_end_x, cape_top_y);
-    ctx.line_to(cape_start_x, cape_top_y);
-
-    let cape_control_x1 = cape_start_x + cape_width * 0.2;
-    let cape_control_y1 = cape_bottom_y + cape_height * 0.2;
-    let cape_control_x2 = cape_start_x + cape_width * 0.65;
-    let cape_control_y2 = cape_bottom_y;
-
-    ctx.move_to(cape_start_x - left_tilt, cape_bottom_y);
-    ctx.bezier_curve_to(
-        cape_control_x1,
-        cape_control_y1,
-        cape_control_x2,
-        cape_control_y2,
-        cape_end_x - left_tilt,
-        cape_bottom_y,
-    );
+    let zx: f64 = z.x.into();
+    let zy: f64 = z.y.into();
+    begin_death_transform(ctx, &z.anim, now, zx, zy + figures.zombie_feet);
+    ctx.translate(zx, zy).unwrap();
+    ctx.set_line_width(skin.theme.line_width);
+    ctx.set_stroke_style_str(color);
+    ctx.stroke_with_path(figures.zombie(&z.anim));
+    ctx.restore();
+}
+
+// Clears the whole view and draws the canvas border.
+pub fn clear(ctx: &web_sys::CanvasRenderingContext2d) {
+    ctx.clear_rect(0.0, 0.0, VIEW_WIDTH, VIEW_HEIGHT);
+    ctx.set_stroke_style_str("black");
+    ctx.stroke_rect(1.0, 1.0, VIEW_WIDTH - 2.0, VIEW_HEIGHT - 2.0);
+}
+
+// Clears the whole view to the theme's background, for the game screens.
+pub fn draw_background(ctx: &web_sys::CanvasRenderingContext2d, palette: &Palette) {
+    ctx.set_fill_style_str(&palette.background);
+    ctx.fill_rect(0.0, 0.0, VIEW_WIDTH, VIEW_HEIGHT);
+    ctx.set_stroke_style_str(&palette.border);
+    ctx.stroke_rect(1.0, 1.0, VIEW_WIDTH - 2.0, VIEW_HEIGHT - 2.0);
+}
+
+pub fn draw_world_bounds(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    world: &World,
+    palette: &Palette,
+) {
+    let (width, height) = (world.width.into(), world.height.into());
+    ctx.set_fill_style_str(&palette.arena);
+    ctx.fill_rect(0.0, 0.0, width, height);
+    ctx.set_stroke_style_str(&palette.world_bounds);
+    ctx.stroke_rect(0.0, 0.0, width, height);
+}
+
+pub fn draw_poo(ctx: &web_sys::CanvasRenderingContext2d, atlas: &Atlas, p: &Poo) {
+    atlas.draw(ctx, "poo", p.x.into(), p.y.into());
+}
+
+pub fn draw_particles(ctx: &web_sys::CanvasRenderingContext2d, particles: &ParticleSystem) {
+    for p in particles.particles() {
+        ctx.set_global_alpha(p.alpha().into());
+        ctx.set_fill_style_str(p.color);
+        let size: f64 = p.size.into();
+        ctx.fill_rect(
+            f64::from(p.x) - size / 2.0,
+            f64::from(p.y) - size / 2.0,
+            size,
+            size,
+        );
+    }
+    ctx.set_global_alpha(1.0);
+}
+
+pub fn draw_loading(ctx: &web_sys::CanvasRenderingContext2d, loaded: usize, total: usize) {
+    let (width, height) = (VIEW_WIDTH, VIEW_HEIGHT);
+    let bar_width = width / 2.0;
+    let bar_x = (width - bar_width) / 2.0;
+    let bar_y = height / 2.0;
+    clear(ctx);
+    ctx.set_fill_style_str("black");
+    ctx.set_font("20px sans-serif");
+    ctx.set_text_align("center");
+    ctx.fill_text("Loading...", width / 2.0, bar_y - 20.0)
+        .unwrap();
+    ctx.set_stroke_style_str("black");
+    ctx.stroke_rect(bar_x, bar_y, bar_width, 20.0);
+    ctx.fill_rect(bar_x, bar_y, bar_width * loaded as f64 / total as f64, 20.0);
+}
+
+pub fn draw_load_error(ctx: &web_sys::CanvasRenderingContext2d, message: &str) {
+    let (width, height) = (VIEW_WIDTH, VIEW_HEIGHT);
+    clear(ctx);
+    ctx.set_fill_style_str("red");
+    ctx.set_font("20px sans-serif");
+    ctx.set_text_align("center");
+    ctx.fill_text("Could not start the game", width / 2.0, height / 2.0)
+        .unwrap();
+    ctx.fill_text(message, width / 2.0, height / 2.0 + 30.0)
+        .unwrap();
+}
+
+// Dims the current frame, inside the border, and writes a title and a hint over it.
+fn draw_overlay(ctx: &web_sys::CanvasRenderingContext2d, title: &str, hint: &str) {
+    let (width, height) = (VIEW_WIDTH, VIEW_HEIGHT);
+    ctx.set_fill_style_str("rgba(0, 0, 0, 0.4)");
+    ctx.fill_rect(1.0, 1.0, width - 3.0, height - 2.0);
+    ctx.set_fill_style_str("white");
+    ctx.set_font("48px sans-serif");
+    ctx.set_text_align("center");
+    ctx.fill_text(title, width / 2.0, height / 2.0).unwrap();
+    ctx.set_font("20px sans-serif");
+    ctx.fill_text(hint, width / 2.0, height / 2.0 + 40.0)
+        .unwrap();
+}
 
-    ctx.fill();
-}
-
-pub fn draw_zombie(ctx: &web_sys::CanvasRenderingContext2d, h: &Zombie, c: &str) {
-    ctx.set_stroke_style(&JsValue::from_str(c));
-    //ctx.fill_rect(h.x.into(), h.y.into(), 20.0, 20.0);
-    let zx: f64 = h.x.into();
-    let zy: f64 = h.y.into();
-    // Set the starting position for drawing the human figure
-    let start_x = zx; //rect_width / 2.0;
-    let start_y = zy; //rect_height / 6.0;
-                      // Set the sizes for different body parts
-    let head_radius = 4.0;
-    let body_height = 12.0;
-    let leg_height = 12.0;
-    let arm_width = 8.0;
-    // Set the color and line width for the path
-    //ctx.line_width = 2;
-    // Begin drawing the path
-    ctx.begin_path();
-    // Draw the head
-    ctx.arc(
-        start_x,
-        start_y,
-        head_radius,
-        0.0,
-        std::f64::consts::PI * 2.0,
+// The local leaderboard as a table from top, the run just entered highlighted.
+fn draw_high_scores(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    scores: &HighScores,
+    highlight: Option<usize>,
+    top: f64,
+    color: &str,
+) {
+    let x = VIEW_WIDTH / 2.0 - 250.0;
+    let columns = [0.0, 40.0, 220.0, 300.0, 370.0, 440.0];
+    ctx.set_text_align("left");
+    ctx.set_font("bold 16px sans-serif");
+    ctx.set_fill_style_str(color);
+    if scores.entries().is_empty() {
+        ctx.fill_text("no high scores yet", x, top).unwrap();
+        return;
+    }
+    for (header, column) in ["#", "name", "score", "level", "time", "date"]
+        .iter()
+        .zip(columns)
+    {
+        ctx.fill_text(header, x + column, top).unwrap();
+    }
+    for (i, entry) in scores.entries().iter().enumerate() {
+        let y = top + 24.0 * (i + 1) as f64;
+        if highlight == Some(i) {
+            ctx.set_font("bold 16px sans-serif");
+            ctx.set_fill_style_str("gold");
+        } else {
+            ctx.set_font("16px sans-serif");
+            ctx.set_fill_style_str(color);
+        }
+        let date = js_sys::Date::new(&entry.date.into());
+        let cells = [
+            format!("{}", i + 1),
+            entry.name.clone(),
+            format!("{}", entry.score),
+            format!("{}", entry.level),
+            format_time(entry.duration),
+            format!(
+                "{}-{:02}-{:02}",
+                date.get_full_year(),
+                date.get_month() + 1,
+                date.get_date()
+            ),
+        ];
+        for (cell, column) in cells.iter().zip(columns) {
+            ctx.fill_text(cell, x + column, y).unwrap();
+        }
+    }
+}
+
+// saved_level is the level of the run left unfinished, if any.
+pub fn draw_title(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    scores: &HighScores,
+    achievements: &Achievements,
+    saved_level: Option<u16>,
+) {
+    let width = VIEW_WIDTH;
+    clear(ctx);
+    ctx.set_fill_style_str("green");
+    ctx.set_font("64px sans-serif");
+    ctx.set_text_align("center");
+    ctx.fill_text("Herpooles", width / 2.0, 160.0).unwrap();
+    ctx.set_fill_style_str("black");
+    ctx.set_font("20px sans-serif");
+    ctx.fill_text(
+        "arrows to walk, space or click to throw poo",
+        width / 2.0,
+        220.0,
     )
     .unwrap();
-    // Draw the body
-    ctx.move_to(start_x, start_y + head_radius);
-    ctx.line_to(start_x, start_y + head_radius + body_height);
-    // Draw the legs
-    ctx.move_to(start_x, start_y + head_radius + body_height);
-    ctx.line_to(
-        start_x - arm_width / 2.0,
-        start_y + head_radius + body_height + leg_height,
-    );
-    ctx.move_to(start_x, start_y + head_radius + body_height);
-    ctx.line_to(
-        start_x + arm_width / 2.0,
-        start_y + head_radius + body_height + leg_height,
-    );
-    // Draw the arms
-    ctx.move_to(
-        start_x - arm_width / 2.0,
-        start_y + head_radius + body_height / 2.0,
-    );
-    ctx.line_to(
-        start_x - arm_width,
-        start_y + head_radius + body_height / 2.0,
+    ctx.fill_text(
+        "press Enter to start, 2 for two players (WASD and space, arrows and right Shift)",
+        width / 2.0,
+        250.0,
+    )
+    .unwrap();
+    ctx.fill_text(
+        &format!(
+            "or V for two players against each other, for {} levels",
+            versus::LEVELS
+        ),
+        width / 2.0,
+        280.0,
+    )
+    .unwrap();
+    if let Some(level) = saved_level {
+        ctx.set_fill_style_str("brown");
+        ctx.fill_text(
+            &format!("press C to continue on level {}", level),
+            width / 2.0,
+            280.0,
+        )
+        .unwrap();
+    }
+    draw_high_scores(ctx, scores, None, 320.0, "black");
+    draw_achievements(ctx, achievements, 620.0);
+}
+
+// Every achievement with its progress, the unlocked ones in gold.
+fn draw_achievements(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    achievements: &Achievements,
+    top: f64,
+) {
+    let x = VIEW_WIDTH / 2.0 - 250.0;
+    ctx.set_text_align("left");
+    ctx.set_font("bold 16px sans-serif");
+    ctx.set_fill_style_str("black");
+    ctx.fill_text(
+        &format!(
+            "achievements {}/{}",
+            achievements.unlocked_count(),
+            ACHIEVEMENTS.len()
+        ),
+        x,
+        top,
+    )
+    .unwrap();
+    ctx.set_font("16px sans-serif");
+    for (i, achievement) in ACHIEVEMENTS.iter().enumerate() {
+        let y = top + 24.0 * (i + 1) as f64;
+        let target = achievement.goal.target();
+        let line = if achievements.is_unlocked(achievement.id) {
+            ctx.set_fill_style_str("darkgoldenrod");
+            format!("{}: {}", achievement.title, achievement.description)
+        } else {
+            ctx.set_fill_style_str("grey");
+            format!(
+                "{}: {} ({}/{})",
+                achievement.title,
+                achievement.description,
+                achievements.progress(achievement),
+                target
+            )
+        };
+        ctx.fill_text(&line, x, y).unwrap();
+    }
+}
+
+// The countdown shows whole seconds left, 3, 2, 1.
+pub fn draw_level_intro(ctx: &web_sys::CanvasRenderingContext2d, level: u16, remaining: f64) {
+    draw_overlay(
+        ctx,
+        &format!("Level {}", level),
+        &format!("{}", remaining.ceil().max(1.0)),
     );
-    ctx.move_to(
-        start_x + arm_width / 2.0,
-        start_y + head_radius + body_height / 2.0,
+}
+
+// Until an online game runs: connecting, waiting for players or what went wrong.
+pub fn draw_online_status(ctx: &web_sys::CanvasRenderingContext2d, status: &str) {
+    draw_overlay(ctx, "Online co-op", status);
+}
+
+pub fn draw_watching_status(ctx: &web_sys::CanvasRenderingContext2d, status: &str) {
+    draw_overlay(ctx, "Watching", status);
+}
+
+pub fn draw_paused(ctx: &web_sys::CanvasRenderingContext2d) {
+    draw_overlay(
+        ctx,
+        "Paused",
+        "press P to resume, R to restart or Q to quit",
     );
-    ctx.line_to(
-        start_x + arm_width,
-        start_y + head_radius + body_height / 2.0,
+}
+
+// The cursor blinks on the wall clock, the simulation is stopped by then.
+pub fn draw_name_entry(ctx: &web_sys::CanvasRenderingContext2d, score: u32, name: &str) {
+    let cursor = if ((js_sys::Date::now() / 500.0) as u64).is_multiple_of(2) {
+        "_"
+    } else {
+        " "
+    };
+    draw_overlay(
+        ctx,
+        &format!("New high score: {}!", score),
+        "type your name and press Enter",
     );
+    ctx.set_font("32px sans-serif");
+    ctx.fill_text(
+        &format!("{}{}", name, cursor),
+        VIEW_WIDTH / 2.0,
+        VIEW_HEIGHT / 2.0 + 100.0,
+    )
+    .unwrap();
+}
 
-    ctx.close_path();
-    ctx.stroke();
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
 }
 
-pub fn draw_poo(ctx: &web_sys::CanvasRenderingContext2d, p: &Poo) {
-    // TODO: load the image once in main
-    let document = web_sys::window().unwrap().document().unwrap();
-    let image_element = document
-        .create_element("img")
-        .unwrap()
-        .dyn_into::<web_sys::HtmlImageElement>()
-        .unwrap();
-    image_element.set_src("resources/poop.png");
-    ctx.draw_image_with_html_image_element(&image_element, p.x.into(), p.y.into())
-        .unwrap();
+// The statistics of the run that just ended, centered lines from top.
+// The deaths are told apart by player when there were more heroes.
+fn draw_stats(ctx: &web_sys::CanvasRenderingContext2d, stats: &Stats, players: usize, top: f64) {
+    let accuracy = match stats.accuracy() {
+        Some(accuracy) => format!(
+            "accuracy {:.0}% ({} of {} poo)",
+            accuracy * 100.0,
+            stats.hits,
+            stats.shots
+        ),
+        None => "no poo thrown".to_string(),
+    };
+    let closest = stats
+        .closest_call
+        .map_or("no zombie in sight".to_string(), |d| {
+            format!("closest call {:.0}", d)
+        });
+    let mut lines = vec![format!(
+        "{}, walked {:.0}, {}",
+        accuracy, stats.distance, closest
+    )];
+    // the last levels, as many as fit on a line
+    let levels: Vec<String> = stats
+        .levels
+        .iter()
+        .rev()
+        .take(5)
+        .rev()
+        .map(|l| format!("L{}: {} in {}", l.level, l.kills, format_time(l.duration)))
+        .collect();
+    lines.push(levels.join("   "));
+    for death in &stats.deaths {
+        let who = match players {
+            1 => String::new(),
+            _ => format!("P{} ", death.player + 1),
+        };
+        lines.push(format!(
+            "{}got by a zombie on level {} at ({:.0}, {:.0}) after {}",
+            who,
+            death.level,
+            death.x,
+            death.y,
+            format_time(death.time)
+        ));
+    }
+    ctx.set_fill_style_str("white");
+    ctx.set_font("18px sans-serif");
+    ctx.set_text_align("center");
+    for (i, line) in lines.iter().enumerate() {
+        ctx.fill_text(line, VIEW_WIDTH / 2.0, top + 26.0 * i as f64)
+            .unwrap();
+    }
+}
+
+// player_scores has the score of every hero, only shown in co-op.
+pub fn draw_game_over(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    score: u32,
+    heroes: &[Herpooles],
+    controller: &Controller,
+    scores: &HighScores,
+    rank: Option<usize>,
+) {
+    let mut title = format!("Game over! Score: {}", score);
+    if heroes.len() > 1 {
+        let each: Vec<String> = heroes
+            .iter()
+            .map(|h| format!("P{} {}", h.player + 1, h.score))
+            .collect();
+        title = format!("{} ({})", title, each.join(", "));
+    }
+    match versus::outcome(heroes, controller) {
+        Some(Outcome::Winner(player)) => title = format!("Player {} wins!", player + 1),
+        Some(Outcome::Draw) => title = "Draw!".to_string(),
+        None => {}
+    }
+    draw_overlay(ctx, &title, "press Enter or R to play again, Q to quit");
+    if controller.rules() == Rules::Versus {
+        draw_rivals(ctx, heroes, VIEW_HEIGHT / 2.0 + 90.0);
+    }
+    draw_stats(
+        ctx,
+        &controller.stats,
+        heroes.len(),
+        VIEW_HEIGHT / 2.0 - 140.0,
+    );
+    if controller.rules() == Rules::Coop {
+        draw_high_scores(ctx, scores, rank, VIEW_HEIGHT / 2.0 + 90.0, "white");
+    }
+}
+
+// What each rival did in a versus run, under the winner.
+fn draw_rivals(ctx: &web_sys::CanvasRenderingContext2d, heroes: &[Herpooles], top: f64) {
+    ctx.set_fill_style_str("white");
+    ctx.set_font("20px sans-serif");
+    ctx.set_text_align("center");
+    for (i, h) in heroes.iter().enumerate() {
+        let line = format!(
+            "Player {}: {} zombies, {} hits on the other",
+            h.player + 1,
+            h.score,
+            h.stuns
+        );
+        ctx.fill_text(&line, VIEW_WIDTH / 2.0, top + 30.0 * i as f64)
+            .unwrap();
+    }
 }
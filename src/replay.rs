@@ -0,0 +1,285 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{self, Controller, Direction, Herpooles, Input, Rules, MAX_PLAYERS};
+use crate::PressedKeys;
+
+// Longest run a replay may hold, in ticks: an hour of play.
+pub const MAX_TICKS: u64 = 60 * 60 * 60;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    Classic,
+    // two heroes on one keyboard
+    Coop,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 2] = [Mode::Classic, Mode::Coop];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Coop => "coop",
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        match self {
+            Mode::Classic => 1,
+            Mode::Coop => 2,
+        }
+    }
+
+    pub fn for_players(players: usize) -> Option<Mode> {
+        Mode::ALL.into_iter().find(|m| m.players() == players)
+    }
+}
+
+// One bit per key, one for fire and two for the bearing.
+fn encode(input: &Input) -> u8 {
+    let keys = &input.keys;
+    let bearing = match input.bearing {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    };
+    u8::from(keys.right)
+        | u8::from(keys.left) << 1
+        | u8::from(keys.up) << 2
+        | u8::from(keys.down) << 3
+        | u8::from(input.fire) << 4
+        | bearing << 5
+}
+
+fn decode(byte: u8) -> Option<Input> {
+    if byte >> 7 != 0 {
+        return None;
+    }
+    let bearing = match (byte >> 5) & 3 {
+        0 => Direction::North,
+        1 => Direction::East,
+        2 => Direction::South,
+        _ => Direction::West,
+    };
+    Some(Input {
+        keys: PressedKeys {
+            right: byte & 1 != 0,
+            left: byte & 2 != 0,
+            up: byte & 4 != 0,
+            down: byte & 8 != 0,
+        },
+        fire: byte & 16 != 0,
+        bearing,
+    })
+}
+
+fn one() -> usize {
+    1
+}
+
+// A run as its seed and the input of every hero on every tick, the heroes in order. Keys
+// are held for many ticks in a row, so the inputs are run-length encoded as (input, count)
+// pairs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    #[serde(default = "one")]
+    pub players: usize,
+    #[serde(default)]
+    pub rules: Rules,
+    // zombies every level starts with, when the debug overlay set them, see Controller::crowd
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub horde: Option<u16>,
+    runs: Vec<(u8, u32)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, players: usize) -> Replay {
+        Replay {
+            seed,
+            players,
+            rules: Rules::Coop,
+            horde: None,
+            runs: vec![],
+        }
+    }
+
+    pub fn push(&mut self, input: Input) {
+        let byte = encode(&input);
+        match self.runs.last_mut() {
+            Some((last, count)) if *last == byte && *count < u32::MAX => *count += 1,
+            _ => self.runs.push((byte, 1)),
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        let inputs: u64 = self.runs.iter().map(|&(_, count)| u64::from(count)).sum();
+        inputs / self.players.max(1) as u64
+    }
+
+    // The inputs from a tick on, as a replay of the same run.
+    pub fn since(&self, tick: u64) -> Replay {
+        let mut skip = tick.saturating_mul(self.players as u64);
+        let mut runs = vec![];
+        for &(byte, count) in &self.runs {
+            let count = u64::from(count);
+            if skip >= count {
+                skip -= count;
+                continue;
+            }
+            runs.push((byte, (count - skip) as u32));
+            skip = 0;
+        }
+        Replay {
+            seed: self.seed,
+            players: self.players,
+            rules: self.rules,
+            horde: self.horde,
+            runs,
+        }
+    }
+
+    // Whether both are of the same run, the one maybe further on than the other.
+    pub fn same_run(&self, other: &Replay) -> bool {
+        self.seed == other.seed
+            && self.players == other.players
+            && self.rules == other.rules
+            && self.horde == other.horde
+    }
+
+    // Puts the inputs of later, see since, after these.
+    pub fn append(&mut self, later: &Replay) {
+        for &(byte, count) in &later.runs {
+            match self.runs.last_mut() {
+                Some((last, total)) if *last == byte && total.checked_add(count).is_some() => {
+                    *total += count
+                }
+                _ => self.runs.push((byte, count)),
+            }
+        }
+    }
+
+    // Every input in order, the heroes of a tick one after the other. None for an input that
+    // could not have been recorded.
+    pub fn inputs(&self) -> impl Iterator<Item = Option<Input>> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|&(byte, count)| std::iter::repeat_n(decode(byte), count as usize))
+    }
+}
+
+// What a finished run came to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub score: u32,
+    pub level: u16,
+    pub ticks: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplayError {
+    TooLong,
+    BadPlayers,
+    BadInput,
+    // herpooles is still standing when the inputs run out
+    Unfinished,
+    // inputs left after herpooles is gone
+    TrailingInput,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ReplayError::TooLong => "replay is too long",
+            ReplayError::BadPlayers => "replay has no or too many players",
+            ReplayError::BadInput => "replay has an invalid input",
+            ReplayError::Unfinished => "run does not end",
+            ReplayError::TrailingInput => "replay goes on after the run ended",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+// Plays a run again tick by tick, up to herpooles being gone.
+pub fn simulate(replay: &Replay) -> Result<Outcome, ReplayError> {
+    let players = replay.players;
+    if !(1..=MAX_PLAYERS).contains(&players) {
+        return Err(ReplayError::BadPlayers);
+    }
+    let ticks = replay.ticks();
+    if ticks > MAX_TICKS {
+        return Err(ReplayError::TooLong);
+    }
+    let mut heroes = Herpooles::team(players);
+    let mut controller = Controller::for_replay(replay);
+    let mut gone = false;
+    let mut inputs = replay.inputs();
+    let mut tick = Vec::with_capacity(players);
+    while let Some(first) = inputs.next() {
+        if gone {
+            return Err(ReplayError::TrailingInput);
+        }
+        tick.clear();
+        for i in 0..players {
+            // a tick cut short is as bad as a made up input
+            let input = if i == 0 {
+                first
+            } else {
+                inputs.next().flatten()
+            };
+            tick.push(input.ok_or(ReplayError::BadInput)?);
+        }
+        game::tick(&mut heroes, &tick, &mut controller);
+        gone = game::run_over(&heroes, &controller);
+    }
+    if !gone {
+        return Err(ReplayError::Unfinished);
+    }
+    Ok(Outcome {
+        score: controller.score,
+        level: controller.level(),
+        ticks,
+    })
+}
+
+// A finished run sent to a scoreboard server. The server trusts nothing but the replay.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub mode: Mode,
+    pub name: String,
+    pub score: u32,
+    pub level: u16,
+    pub replay: Replay,
+}
+
+impl Submission {
+    // Simulates the replay and checks it comes to the claimed score and level.
+    pub fn verify(&self) -> Result<Outcome, String> {
+        if self.replay.rules != Rules::Coop {
+            return Err("versus runs have no scoreboard".to_string());
+        }
+        if self.replay.horde.is_some() {
+            return Err("runs with a set horde have no scoreboard".to_string());
+        }
+        if self.replay.players != self.mode.players() {
+            return Err(format!(
+                "{} is for {} players, the replay has {}",
+                self.mode.name(),
+                self.mode.players(),
+                self.replay.players
+            ));
+        }
+        let outcome = simulate(&self.replay).map_err(|e| e.to_string())?;
+        if outcome.score != self.score || outcome.level != self.level {
+            return Err(format!(
+                "replay scores {} on level {}, not {} on level {}",
+                outcome.score, outcome.level, self.score, self.level
+            ));
+        }
+        Ok(outcome)
+    }
+}
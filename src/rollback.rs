@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{self, Controller, Event, Herpooles, Input, Rules};
+use crate::PressedKeys;
+
+// Rollback play: every peer runs the whole game from the same seed and only the inputs go
+// around. Inputs of the others that did not come yet are guessed, and when the real ones
+// differ the game goes back to the tick they were for and plays on again from there.
+
+// Ticks a peer may run ahead of the last tick it has every input of. A peer that falls
+// further behind stalls the others until its inputs come.
+pub const MAX_ROLLBACK: u64 = 30;
+
+// The inputs of one player from a tick on. Sent again until the others have them, so a
+// lost message costs nothing but some rolling back.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMessage {
+    pub player: usize,
+    // tick of the first input
+    pub first: u64,
+    pub inputs: Vec<Input>,
+    // the inputs the sender has of every player
+    pub acks: Vec<u64>,
+}
+
+// The whole simulation, cheap enough to keep one for every tick not confirmed yet.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub heroes: Vec<Herpooles>,
+    pub controller: Controller,
+}
+
+impl Frame {
+    pub fn new(seed: u64, players: usize) -> Frame {
+        Frame {
+            heroes: Herpooles::team(players),
+            controller: Controller::with_seed(seed, players, Rules::Coop),
+        }
+    }
+
+    // One tick with one input per hero.
+    pub fn step(&mut self, inputs: &[Input]) -> Vec<Event> {
+        game::tick(&mut self.heroes, inputs, &mut self.controller)
+    }
+}
+
+pub struct Rollback {
+    pub player: usize,
+    // inputs of every player from the first tick on, as far as they came
+    inputs: Vec<Vec<Input>>,
+    // inputs of this player every peer is known to have
+    acked: Vec<u64>,
+    // the frame before every tick from first on, up to the current tick
+    history: VecDeque<Frame>,
+    first: u64,
+    current: Frame,
+    tick: u64,
+    // ticks played a second time or more
+    pub resimulated: u64,
+}
+
+impl Rollback {
+    // None unless the player is one of the players.
+    pub fn new(seed: u64, players: usize, player: usize) -> Option<Rollback> {
+        if player >= players {
+            return None;
+        }
+        Some(Rollback {
+            player,
+            inputs: vec![vec![]; players],
+            acked: vec![0; players],
+            history: VecDeque::new(),
+            first: 0,
+            current: Frame::new(seed, players),
+            tick: 0,
+            resimulated: 0,
+        })
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // The game as this peer sees it now, the latest inputs of the others guessed.
+    pub fn frame(&self) -> &Frame {
+        &self.current
+    }
+
+    // Ticks every input is known for, these will not be played again.
+    pub fn confirmed(&self) -> u64 {
+        self.inputs
+            .iter()
+            .map(|i| i.len() as u64)
+            .min()
+            .unwrap_or(0)
+            .min(self.tick)
+    }
+
+    // Plays the next tick with the local input. None while the others are too far behind,
+    // the input is not taken then.
+    pub fn advance(&mut self, local: Input) -> Option<Vec<Event>> {
+        if self.tick - self.confirmed() >= MAX_ROLLBACK {
+            return None;
+        }
+        self.inputs[self.player].push(local);
+        self.history.push_back(self.current.clone());
+        let events = self.current.step(&self.inputs_at(self.tick));
+        self.tick += 1;
+        self.trim();
+        Some(events)
+    }
+
+    // The local inputs the others may not have yet.
+    pub fn message(&self) -> InputMessage {
+        let first = (0..self.acked.len())
+            .filter(|p| *p != self.player)
+            .map(|p| self.acked[p])
+            .min()
+            .unwrap_or(self.tick)
+            .min(self.inputs[self.player].len() as u64);
+        InputMessage {
+            player: self.player,
+            first,
+            inputs: self.inputs[self.player][first as usize..].to_vec(),
+            acks: self.inputs.iter().map(|i| i.len() as u64).collect(),
+        }
+    }
+
+    // Takes the inputs of another peer and plays again from the first one that was guessed
+    // wrong. Inputs after a gap are dropped, they come again with the next message.
+    pub fn receive(&mut self, message: &InputMessage) {
+        let player = message.player;
+        if player == self.player || player >= self.inputs.len() {
+            return;
+        }
+        if let Some(&ack) = message.acks.get(self.player) {
+            // never more than was sent, whatever the peer claims
+            let ack = ack.min(self.inputs[self.player].len() as u64);
+            self.acked[player] = self.acked[player].max(ack);
+        }
+        let have = self.inputs[player].len() as u64;
+        if message.first > have {
+            return;
+        }
+        let guess = self.guess(player);
+        let new = message
+            .inputs
+            .iter()
+            .skip((have - message.first) as usize)
+            .copied();
+        let mut wrong = None;
+        for (tick, input) in (have..).zip(new) {
+            if tick < self.tick && wrong.is_none() && input != guess {
+                wrong = Some(tick);
+            }
+            self.inputs[player].push(input);
+        }
+        if let Some(tick) = wrong {
+            self.roll_back(tick);
+        }
+        self.trim();
+    }
+
+    // What a player is taken to do until its input comes: the same as last, without fire.
+    fn guess(&self, player: usize) -> Input {
+        match self.inputs[player].last() {
+            Some(last) => Input {
+                fire: false,
+                ..*last
+            },
+            None => Input::capture(&Herpooles::new(), PressedKeys::default()),
+        }
+    }
+
+    fn inputs_at(&self, tick: u64) -> Vec<Input> {
+        (0..self.inputs.len())
+            .map(|p| match self.inputs[p].get(tick as usize) {
+                Some(input) => *input,
+                None => self.guess(p),
+            })
+            .collect()
+    }
+
+    fn roll_back(&mut self, tick: u64) {
+        let back = (tick - self.first) as usize;
+        self.current = self.history[back].clone();
+        self.history.truncate(back);
+        for t in tick..self.tick {
+            self.history.push_back(self.current.clone());
+            self.current.step(&self.inputs_at(t));
+            self.resimulated += 1;
+        }
+    }
+
+    // Frames of confirmed ticks are not needed any more.
+    fn trim(&mut self) {
+        let confirmed = self.confirmed();
+        while self.first < confirmed {
+            self.history.pop_front();
+            self.first += 1;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const STORAGE_KEY: &str = "herpooles.scores";
+// Bumped when the stored layout changes. Tables stored by another version are dropped
+// rather than misread.
+const SCHEMA_VERSION: u32 = 1;
+
+// Runs kept in the table.
+pub const MAX_ENTRIES: usize = 10;
+// Characters in a name.
+pub const MAX_NAME: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    // reached
+    pub level: u16,
+    // simulation seconds the run lasted
+    pub duration: f64,
+    // milliseconds since the epoch, as Date.now()
+    pub date: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    version: u32,
+    last_name: String,
+    entries: Vec<Entry>,
+}
+
+// The local leaderboard, best score first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighScores {
+    entries: Vec<Entry>,
+    // offered again on the next name entry
+    pub last_name: String,
+}
+
+impl HighScores {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    // Whether a run with this score would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES
+                || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    // Adds a run and returns its place in the table, if it made it. Among equal scores the
+    // older run keeps the better place.
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        self.last_name = entry.name.clone();
+        let rank = self.entries.partition_point(|e| e.score >= entry.score);
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    fn stored(&self) -> Stored {
+        Stored {
+            version: SCHEMA_VERSION,
+            last_name: self.last_name.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+
+    fn from_stored(stored: Stored) -> Result<HighScores, String> {
+        if stored.version != SCHEMA_VERSION {
+            return Err(format!("unknown high score version {}", stored.version));
+        }
+        let mut entries = stored.entries;
+        entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+        entries.truncate(MAX_ENTRIES);
+        Ok(HighScores {
+            entries,
+            last_name: stored.last_name,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.stored()).expect("serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<HighScores, String> {
+        let stored: Stored = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        HighScores::from_stored(stored)
+    }
+
+    pub fn load() -> HighScores {
+        let Some(stored) = storage::load::<Stored>(STORAGE_KEY) else {
+            return HighScores::default();
+        };
+        HighScores::from_stored(stored).unwrap_or_else(|e| {
+            log!("ignoring stored high scores: {}", e);
+            HighScores::default()
+        })
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.stored());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::callbacks::KeyboardCodes;
+use crate::game::{Rules, MAX_PLAYERS};
+use crate::scores::MAX_NAME;
+
+// Seconds the "Level N" screen counts down before the zombies start moving.
+pub const LEVEL_COUNTDOWN: f64 = 3.0;
+
+// What the player can ask for outside of moving and firing, whatever the device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    // start from the title, resume from the pause, play again from the game over
+    Confirm,
+    // start a co-op run from the title
+    TwoPlayers,
+    // start a run of two players against each other from the title
+    Versus,
+    // carry on with the saved run from the title
+    Continue,
+    Pause,
+    Restart,
+    Quit,
+    // typing a name for the high scores
+    Type(char),
+    Erase,
+}
+
+// The keys the heroes fire with, space for the first and the right shift for the second.
+// None of them is a screen key, firing never resumes, confirms or starts anything.
+pub const FIRE_KEYS: [u32; MAX_PLAYERS] =
+    [KeyboardCodes::Spacebar as u32, KeyboardCodes::Shift as u32];
+
+// The action of a key, by its key code.
+pub fn key_action(code: u32) -> Option<Action> {
+    if code == KeyboardCodes::Enter as u32 {
+        Some(Action::Confirm)
+    } else if code == KeyboardCodes::C as u32 {
+        Some(Action::Continue)
+    } else if code == KeyboardCodes::Two as u32 {
+        Some(Action::TwoPlayers)
+    } else if code == KeyboardCodes::V as u32 {
+        Some(Action::Versus)
+    } else if code == KeyboardCodes::P as u32 || code == KeyboardCodes::Escape as u32 {
+        Some(Action::Pause)
+    } else if code == KeyboardCodes::R as u32 {
+        Some(Action::Restart)
+    } else if code == KeyboardCodes::Q as u32 {
+        Some(Action::Quit)
+    } else {
+        None
+    }
+}
+
+// What the caller has to do after a screen change.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transition {
+    None,
+    // a run for this many heroes
+    Start { players: usize, rules: Rules },
+    // reset the heroes and the controller, for as many players and the rules as before
+    NewRun,
+    // restore the saved run, see snapshot::Snapshot
+    Resume,
+    // add the finished run to the high scores under this name, see Screen::set_rank
+    NameEntered(String),
+}
+
+// The screen state machine:
+// Title -> LevelIntro (or Paused, continuing a saved run) -> Playing -> LevelIntro -> ... -> (EnterName) -> GameOver
+// -> LevelIntro or Title.
+// LevelIntro and Playing can be paused and resumed. The simulation only runs while Playing.
+// EnterName comes first when the score makes the high scores.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Screen {
+    Title,
+    LevelIntro { level: u16, remaining: f64 },
+    Playing,
+    Paused { from: Box<Screen> },
+    EnterName { score: u32, name: String },
+    // rank is the place of this run in the high scores, if it made it
+    GameOver { score: u32, rank: Option<usize> },
+}
+
+impl Screen {
+    fn level_intro(level: u16) -> Screen {
+        Screen::LevelIntro {
+            level,
+            remaining: LEVEL_COUNTDOWN,
+        }
+    }
+
+    // Applies a player action.
+    pub fn handle(&mut self, action: Action) -> Transition {
+        if let Screen::EnterName { score, name } = self {
+            match action {
+                Action::Type(c) if name.chars().count() < MAX_NAME => name.push(c),
+                Action::Erase => {
+                    name.pop();
+                }
+                Action::Confirm => {
+                    let name = match name.trim() {
+                        "" => "anonymous".to_string(),
+                        trimmed => trimmed.to_string(),
+                    };
+                    *self = Screen::GameOver {
+                        score: *score,
+                        rank: None,
+                    };
+                    return Transition::NameEntered(name);
+                }
+                _ => {}
+            }
+            return Transition::None;
+        }
+        let (next, transition) = match (&*self, action) {
+            (Screen::Title, Action::Confirm) => (
+                Screen::level_intro(1),
+                Transition::Start {
+                    players: 1,
+                    rules: Rules::Coop,
+                },
+            ),
+            (Screen::Title, Action::TwoPlayers) => (
+                Screen::level_intro(1),
+                Transition::Start {
+                    players: 2,
+                    rules: Rules::Coop,
+                },
+            ),
+            (Screen::Title, Action::Versus) => (
+                Screen::level_intro(1),
+                Transition::Start {
+                    players: 2,
+                    rules: Rules::Versus,
+                },
+            ),
+            // the player gets to look at where the run was before it carries on
+            (Screen::Title, Action::Continue) => (
+                Screen::Paused {
+                    from: Box::new(Screen::Playing),
+                },
+                Transition::Resume,
+            ),
+            (Screen::LevelIntro { .. } | Screen::Playing, Action::Pause) => (
+                Screen::Paused {
+                    from: Box::new(self.clone()),
+                },
+                Transition::None,
+            ),
+            (Screen::Playing, Action::Restart) => (Screen::level_intro(1), Transition::NewRun),
+            (Screen::Paused { from }, Action::Pause | Action::Confirm) => {
+                ((**from).clone(), Transition::None)
+            }
+            (Screen::Paused { .. } | Screen::GameOver { .. }, Action::Restart) => {
+                (Screen::level_intro(1), Transition::NewRun)
+            }
+            (Screen::GameOver { .. }, Action::Confirm) => {
+                (Screen::level_intro(1), Transition::NewRun)
+            }
+            (Screen::Paused { .. } | Screen::GameOver { .. }, Action::Quit) => {
+                (Screen::Title, Transition::None)
+            }
+            _ => return Transition::None,
+        };
+        *self = next;
+        transition
+    }
+
+    // Applies a player action in a room or while watching. The runs there are not ours to
+    // start, so only pausing and quitting are left.
+    pub fn handle_online(&mut self, action: Action) -> Transition {
+        let mut next = self.clone();
+        match next.handle(action) {
+            Transition::Start { .. } | Transition::NewRun | Transition::Resume => Transition::None,
+            transition => {
+                *self = next;
+                transition
+            }
+        }
+    }
+
+    // Pauses if there is anything to pause, unlike Action::Pause it never resumes.
+    pub fn pause(&mut self) {
+        if self.is_pausable() {
+            self.handle(Action::Pause);
+        }
+    }
+
+    pub fn is_pausable(&self) -> bool {
+        matches!(self, Screen::LevelIntro { .. } | Screen::Playing)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        *self == Screen::Playing
+    }
+
+    // Counts the level intro down. dt in seconds.
+    pub fn update(&mut self, dt: f64) {
+        if let Screen::LevelIntro { remaining, .. } = self {
+            *remaining -= dt;
+            if *remaining <= 0.0 {
+                *self = Screen::Playing;
+            }
+        }
+    }
+
+    // The controller moved on to a new level.
+    pub fn level_started(&mut self, level: u16) {
+        if self.is_playing() {
+            *self = Screen::level_intro(level);
+        }
+    }
+
+    // Herpooles is gone.
+    pub fn game_over(&mut self, score: u32) {
+        if self.is_playing() {
+            *self = Screen::GameOver { score, rank: None };
+        }
+    }
+
+    // Herpooles is gone with a high score, name is offered to be edited.
+    pub fn enter_name(&mut self, score: u32, name: &str) {
+        if self.is_playing() {
+            *self = Screen::EnterName {
+                score,
+                name: name.to_string(),
+            };
+        }
+    }
+
+    // Where the run just named landed in the high scores, to highlight it.
+    pub fn set_rank(&mut self, place: Option<usize>) {
+        if let Screen::GameOver { rank, .. } = self {
+            *rank = place;
+        }
+    }
+}
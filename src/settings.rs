@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const STORAGE_KEY: &str = "herpooles.settings";
+
+// Volumes are gains between 0 and 1.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+    pub muted: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            master: 0.8,
+            music: 0.5,
+            effects: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let settings: Settings = storage::load(STORAGE_KEY).unwrap_or_default();
+        Settings {
+            master: settings.master.clamp(0.0, 1.0),
+            music: settings.music.clamp(0.0, 1.0),
+            effects: settings.effects.clamp(0.0, 1.0),
+            muted: settings.muted,
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+}
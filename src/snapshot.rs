@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::game::{Controller, Herpooles};
+use crate::storage;
+
+const STORAGE_KEY: &str = "herpooles.snapshot";
+// Bumped when the layout of the game state changes, older snapshots are dropped then.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// A run in the middle of being played: the heroes, the zombies, the poo in flight and the
+// controller, down to its generator and the replay so far. Restoring it carries on exactly
+// where it was taken.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    heroes: Vec<Herpooles>,
+    controller: Controller,
+}
+
+impl Snapshot {
+    pub fn take(heroes: &[Herpooles], controller: &Controller) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            heroes: heroes.to_vec(),
+            controller: controller.clone(),
+        }
+    }
+
+    pub fn level(&self) -> u16 {
+        self.controller.level()
+    }
+
+    pub fn restore(self) -> (Vec<Herpooles>, Controller) {
+        (self.heroes, self.controller)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Snapshot, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or("snapshot has no version")?;
+        if version != u64::from(SNAPSHOT_VERSION) {
+            return Err(format!("unknown snapshot version {}", version));
+        }
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    // The run left unfinished, if any. A snapshot that cannot be read is dropped.
+    pub fn load() -> Option<Snapshot> {
+        let value = storage::load::<Value>(STORAGE_KEY)?;
+        match Snapshot::from_json(&value.to_string()) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                log!("dropping the saved run: {}", e);
+                Snapshot::clear();
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+
+    // The run ended or another one started.
+    pub fn clear() {
+        storage::remove(STORAGE_KEY);
+    }
+}
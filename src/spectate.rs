@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{self, Controller, Event, Herpooles, Input, MAX_PLAYERS};
+use crate::replay::Replay;
+use crate::rollback::Frame;
+
+// Spectating: a game played in one browser is shown read-only in others. The game is
+// deterministic, so what goes out is the replay as it grows, a byte per hero and tick before
+// run-length encoding. Watchers play it again and draw it the way the player sees it. The
+// room server passes it on, see ClientMessage::Broadcast and ClientMessage::Watch.
+
+// Ticks a watcher may be behind the broadcast before it plays them at once, on coming into a
+// run that goes on or after a hiccup.
+pub const MAX_BEHIND: u64 = 30;
+
+// The inputs of a run from a tick on. A run is sent again from its first tick when it
+// restarts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ticks {
+    pub first: u64,
+    pub replay: Replay,
+}
+
+// The broadcasting side: what of the run the watchers do not have yet.
+#[derive(Default)]
+pub struct Broadcast {
+    // the run without its inputs, and how many of its ticks went out
+    sent: Option<(Replay, u64)>,
+}
+
+impl Broadcast {
+    // The ticks played since the last call, None if there are none. Another run, or the
+    // same one continued from further back, goes out from its first tick.
+    pub fn ticks(&mut self, replay: &Replay) -> Option<Ticks> {
+        let ticks = replay.ticks();
+        let first = match &self.sent {
+            Some((run, sent)) if run.same_run(replay) && *sent <= ticks => Some(*sent),
+            _ => None,
+        };
+        if first == Some(ticks) {
+            return None;
+        }
+        let first = first.unwrap_or(0);
+        self.sent = Some((replay.since(ticks), ticks));
+        Some(Ticks {
+            first,
+            replay: replay.since(first),
+        })
+    }
+}
+
+// The watching side: the run played again as its ticks come, at the pace of the game.
+#[derive(Default)]
+pub struct Spectator {
+    frame: Option<Frame>,
+    players: usize,
+    // inputs received and not played yet, the heroes of a tick one after the other
+    pending: VecDeque<Input>,
+    received: u64,
+    played: u64,
+    // frame time not played yet, in seconds
+    lag: f64,
+}
+
+impl Spectator {
+    // Takes the ticks that follow the ones received so far, or a run from its first tick.
+    // Anything else is dropped, the broadcaster sends every tick once and in order.
+    pub fn receive(&mut self, ticks: &Ticks) {
+        let replay = &ticks.replay;
+        if !(1..=MAX_PLAYERS).contains(&replay.players) {
+            return;
+        }
+        let Some(inputs) = replay.inputs().collect::<Option<Vec<Input>>>() else {
+            return;
+        };
+        if !inputs.len().is_multiple_of(replay.players) {
+            return;
+        }
+        if ticks.first == 0 {
+            let controller = Controller::for_replay(replay);
+            *self = Spectator {
+                frame: Some(Frame {
+                    heroes: Herpooles::team(replay.players),
+                    controller,
+                }),
+                players: replay.players,
+                ..Spectator::default()
+            };
+        } else if self.frame.is_none() || ticks.first != self.received {
+            return;
+        }
+        self.received += (inputs.len() / self.players) as u64;
+        self.pending.extend(inputs);
+    }
+
+    // Plays the ticks of the frame time that came, and at once those too far behind.
+    // Returns the events of the ticks played at their pace.
+    pub fn advance(&mut self, elapsed: f64) -> Vec<Event> {
+        let mut events = vec![];
+        if self.frame.is_none() {
+            return events;
+        }
+        while self.behind() > MAX_BEHIND {
+            self.step();
+        }
+        self.lag += elapsed;
+        while self.lag >= game::TICK && self.behind() > 0 {
+            events.extend(self.step());
+            self.lag -= game::TICK;
+        }
+        // time spent waiting for ticks is not made up for
+        if self.behind() == 0 {
+            self.lag = self.lag.min(game::TICK);
+        }
+        events
+    }
+
+    // The run as far as it was played, None before its first ticks came.
+    pub fn frame(&self) -> Option<&Frame> {
+        self.frame.as_ref()
+    }
+
+    // Ticks received and not played yet.
+    pub fn behind(&self) -> u64 {
+        self.received - self.played
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.frame
+            .as_ref()
+            .is_some_and(|f| game::run_over(&f.heroes, &f.controller))
+    }
+
+    fn step(&mut self) -> Vec<Event> {
+        let Some(frame) = self.frame.as_mut() else {
+            return vec![];
+        };
+        let inputs: Vec<Input> = self.pending.drain(..self.players).collect();
+        self.played += 1;
+        game::tick(&mut frame.heroes, &inputs, &mut frame.controller)
+    }
+}
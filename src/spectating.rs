@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::game::{Controller, Event, Herpooles};
+use crate::net::{ClientMessage, ServerMessage};
+use crate::netplay::DEFAULT_SERVER;
+use crate::replay::Replay;
+use crate::spectate::{Broadcast, Spectator};
+
+const STOPPED: &str = "the broadcast stopped";
+
+fn server(params: &web_sys::UrlSearchParams) -> String {
+    params
+        .get("server")
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string())
+}
+
+// Sends the first message once the socket is open.
+fn on_open(socket: &web_sys::WebSocket, first: ClientMessage) {
+    let open_socket = socket.clone();
+    let open_closure = Closure::wrap(Box::new(move || {
+        if let Err(e) = open_socket.send_with_str(&first.to_json()) {
+            log!("could not join: {:?}", e);
+        }
+    }) as Box<dyn Fn()>);
+    socket.set_onopen(Some(open_closure.as_ref().unchecked_ref()));
+    open_closure.forget();
+}
+
+// A game played here, sent to the room server as it goes for others to watch.
+pub struct Broadcaster {
+    socket: web_sys::WebSocket,
+    broadcast: Broadcast,
+}
+
+// ?broadcast=<name> sends the game to whoever watches that room, on the server of
+// ?server=<ws url>.
+pub fn broadcast(params: &web_sys::UrlSearchParams) -> Result<Option<Broadcaster>, JsValue> {
+    let Some(room) = params.get("broadcast") else {
+        return Ok(None);
+    };
+    let socket = web_sys::WebSocket::new(&server(params))?;
+    on_open(&socket, ClientMessage::Broadcast { room });
+    let message_closure = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        let Some(text) = event.data().as_string() else {
+            return;
+        };
+        if let Ok(ServerMessage::Refused { reason }) = serde_json::from_str(&text) {
+            log!("not broadcasting: {}", reason);
+        }
+    }) as Box<dyn Fn(_)>);
+    socket.set_onmessage(Some(message_closure.as_ref().unchecked_ref()));
+    message_closure.forget();
+    Ok(Some(Broadcaster {
+        socket,
+        broadcast: Broadcast::default(),
+    }))
+}
+
+impl Broadcaster {
+    // Sends the ticks of the run played since the last frame.
+    pub fn send(&mut self, replay: &Replay) {
+        if self.socket.ready_state() != web_sys::WebSocket::OPEN {
+            return;
+        }
+        let Some(ticks) = self.broadcast.ticks(replay) else {
+            return;
+        };
+        if let Err(e) = self
+            .socket
+            .send_with_str(&ClientMessage::Ticks(ticks).to_json())
+        {
+            log!("could not broadcast: {:?}", e);
+        }
+    }
+}
+
+// A game played elsewhere, shown here without input.
+pub struct Watcher {
+    socket: web_sys::WebSocket,
+    server: String,
+    room: String,
+    spectator: Spectator,
+    // why the server would not have us, that it went away or the broadcaster did
+    problem: Option<String>,
+}
+
+// ?watch=<name> shows the game broadcast in that room, on the server of ?server=<ws url>.
+pub fn watch(params: &web_sys::UrlSearchParams) -> Result<Option<Rc<RefCell<Watcher>>>, JsValue> {
+    let Some(room) = params.get("watch") else {
+        return Ok(None);
+    };
+    let server = server(params);
+    let socket = web_sys::WebSocket::new(&server)?;
+    on_open(&socket, ClientMessage::Watch { room: room.clone() });
+    let watcher = Rc::new(RefCell::new(Watcher {
+        socket: socket.clone(),
+        server,
+        room,
+        spectator: Spectator::default(),
+        problem: None,
+    }));
+
+    let message_watcher = watcher.clone();
+    let message_closure = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        let Some(text) = event.data().as_string() else {
+            return;
+        };
+        match serde_json::from_str(&text) {
+            Ok(message) => message_watcher.borrow_mut().receive(message),
+            Err(e) => log!("bad message from the room server: {}", e),
+        }
+    }) as Box<dyn Fn(_)>);
+    socket.set_onmessage(Some(message_closure.as_ref().unchecked_ref()));
+    message_closure.forget();
+
+    let close_watcher = watcher.clone();
+    let close_closure = Closure::wrap(Box::new(move || {
+        let mut watcher = close_watcher.borrow_mut();
+        if watcher.problem.is_none() {
+            watcher.problem = Some(format!("lost the connection to {}", watcher.server));
+        }
+    }) as Box<dyn Fn()>);
+    socket.set_onclose(Some(close_closure.as_ref().unchecked_ref()));
+    close_closure.forget();
+    Ok(Some(watcher))
+}
+
+impl Watcher {
+    fn receive(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::Ticks(ticks) => {
+                // a broadcaster came back
+                if ticks.first == 0 && self.problem.as_deref() == Some(STOPPED) {
+                    self.problem = None;
+                }
+                self.spectator.receive(&ticks);
+            }
+            ServerMessage::Refused { reason } => self.problem = Some(reason),
+            ServerMessage::Stopped => self.problem = Some(STOPPED.to_string()),
+            _ => {}
+        }
+    }
+
+    // Plays the broadcast on by the frame time and puts it in heroes and controller.
+    // Returns the events of the ticks played.
+    pub fn frame(
+        &mut self,
+        elapsed: f64,
+        heroes: &mut Vec<Herpooles>,
+        controller: &mut Controller,
+    ) -> Vec<Event> {
+        let events = self.spectator.advance(elapsed);
+        if let Some(frame) = self.spectator.frame() {
+            *heroes = frame.heroes.clone();
+            *controller = frame.controller.clone();
+        }
+        events
+    }
+
+    // What to show over the game until it runs, or once it cannot go on.
+    pub fn status(&self) -> Option<String> {
+        if let Some(problem) = &self.problem {
+            return Some(problem.clone());
+        }
+        match self.spectator.frame() {
+            None => Some(format!("Waiting for a game in {}", self.room)),
+            Some(frame) if self.spectator.is_over() => {
+                Some(format!("The run is over, score {}", frame.controller.score))
+            }
+            Some(_) => None,
+        }
+    }
+
+    pub fn close(&self) {
+        let _ = self.socket.close();
+    }
+}
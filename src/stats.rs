@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+// How a run ended. Zombies reaching herpooles are the only way to die so far.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cause {
+    Zombie,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Death {
+    // the hero who died
+    pub player: usize,
+    pub cause: Cause,
+    pub level: u16,
+    // simulation seconds since the start of the run
+    pub time: f64,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub level: u16,
+    pub kills: u32,
+    // simulation seconds herpooles spent alive on it
+    pub duration: f64,
+    // poo thrown on it
+    pub shots: u32,
+    // times herpooles walked into the edge of the world on it
+    pub edge_touches: u32,
+}
+
+// What happened over a run, collected by game::update so it is part of the run like the
+// score: it is saved with it and comes out the same when a replay is played again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub shots: u32,
+    // shots that killed a zombie
+    pub hits: u32,
+    // in world pixels
+    pub distance: f32,
+    // the nearest a walking zombie came to herpooles, in world pixels
+    pub closest_call: Option<f32>,
+    // the levels played, the current one last
+    pub levels: Vec<LevelStats>,
+    // one for every hero who died, in the order they did
+    pub deaths: Vec<Death>,
+}
+
+impl Stats {
+    pub fn level_started(&mut self, level: u16) {
+        self.levels.push(LevelStats {
+            level,
+            kills: 0,
+            duration: 0.0,
+            shots: 0,
+            edge_touches: 0,
+        });
+    }
+
+    // herpooles was alive for dt more seconds
+    pub fn tick(&mut self, dt: f64) {
+        if let Some(level) = self.levels.last_mut() {
+            level.duration += dt;
+        }
+    }
+
+    pub fn fired(&mut self) {
+        self.shots += 1;
+        if let Some(level) = self.levels.last_mut() {
+            level.shots += 1;
+        }
+    }
+
+    pub fn touched_edge(&mut self) {
+        if let Some(level) = self.levels.last_mut() {
+            level.edge_touches += 1;
+        }
+    }
+
+    // The levels herpooles made it through.
+    pub fn cleared(&self) -> &[LevelStats] {
+        &self.levels[..self.levels.len().saturating_sub(1)]
+    }
+
+    pub fn hit(&mut self) {
+        self.hits += 1;
+        if let Some(level) = self.levels.last_mut() {
+            level.kills += 1;
+        }
+    }
+
+    pub fn walked(&mut self, distance: f32) {
+        self.distance += distance;
+    }
+
+    // The nearest walking zombie is this far away.
+    pub fn zombie_at(&mut self, distance: f32) {
+        self.closest_call = Some(self.closest_call.map_or(distance, |d| d.min(distance)));
+    }
+
+    pub fn died(&mut self, player: usize, cause: Cause, time: f64, x: f32, y: f32) {
+        let level = self.levels.last().map_or(1, |l| l.level);
+        self.deaths.push(Death {
+            player,
+            cause,
+            level,
+            time,
+            x,
+            y,
+        });
+    }
+
+    // Hits per shot, none before the first shot.
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots > 0).then(|| self.hits as f32 / self.shots as f32)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialize")
+    }
+}
@@ -0,0 +1,37 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::window;
+
+// localStorage can be missing or throw (private browsing, disabled storage), in which case
+// nothing is persisted and the callers fall back to their defaults.
+fn local_storage() -> Option<web_sys::Storage> {
+    window().local_storage().ok().flatten()
+}
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let json = local_storage()?.get_item(key).ok()??;
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log!("ignoring stored {}: {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    let json = serde_json::to_string(value).expect("serialize");
+    if storage.set_item(key, &json).is_err() {
+        log!("could not store {}", key);
+    }
+}
+
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
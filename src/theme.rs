@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const STORAGE_KEY: &str = "herpooles.theme";
+
+// Colours are CSS colour strings, sizes are in world pixels.
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    // around the world, and the canvas border
+    pub background: String,
+    pub border: String,
+    // inside the world and its edge
+    pub arena: String,
+    pub world_bounds: String,
+    pub herpooles: String,
+    // the second hero in co-op, the third and fourth only play online
+    pub herpooles_two: String,
+    pub herpooles_three: String,
+    pub herpooles_four: String,
+    pub herpooles_dead: String,
+    pub herpooles_flash: String,
+    pub crown: String,
+    pub cape: String,
+    pub zombie: String,
+    pub zombie_dying: String,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            background: "white".into(),
+            border: "black".into(),
+            arena: "white".into(),
+            world_bounds: "lightgrey".into(),
+            herpooles: "green".into(),
+            herpooles_two: "royalblue".into(),
+            herpooles_three: "darkorange".into(),
+            herpooles_four: "purple".into(),
+            herpooles_dead: "red".into(),
+            herpooles_flash: "white".into(),
+            crown: "brown".into(),
+            cape: "black".into(),
+            zombie: "grey".into(),
+            zombie_dying: "yellow".into(),
+        }
+    }
+}
+
+// Herpooles is drawn at scale times these sizes.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HerpoolesProportions {
+    pub scale: f64,
+    pub head_radius: f64,
+    pub body_height: f64,
+    pub leg_height: f64,
+    pub arm_width: f64,
+    pub crown_width: f64,
+}
+
+impl Default for HerpoolesProportions {
+    fn default() -> Self {
+        HerpoolesProportions {
+            scale: 0.2,
+            head_radius: 20.0,
+            body_height: 100.0,
+            leg_height: 100.0,
+            arm_width: 80.0,
+            crown_width: 50.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZombieProportions {
+    pub head_radius: f64,
+    pub body_height: f64,
+    pub leg_height: f64,
+    pub arm_width: f64,
+}
+
+impl Default for ZombieProportions {
+    fn default() -> Self {
+        ZombieProportions {
+            head_radius: 4.0,
+            body_height: 12.0,
+            leg_height: 12.0,
+            arm_width: 8.0,
+        }
+    }
+}
+
+impl Palette {
+    // Every colour by its field name.
+    fn colors(&self) -> [(&'static str, &str); 14] {
+        [
+            ("background", &self.background),
+            ("border", &self.border),
+            ("arena", &self.arena),
+            ("world_bounds", &self.world_bounds),
+            ("herpooles", &self.herpooles),
+            ("herpooles_two", &self.herpooles_two),
+            ("herpooles_three", &self.herpooles_three),
+            ("herpooles_four", &self.herpooles_four),
+            ("herpooles_dead", &self.herpooles_dead),
+            ("herpooles_flash", &self.herpooles_flash),
+            ("crown", &self.crown),
+            ("cape", &self.cape),
+            ("zombie", &self.zombie),
+            ("zombie_dying", &self.zombie_dying),
+        ]
+    }
+
+    // The colour of a hero by player.
+    pub fn player(&self, player: usize) -> &str {
+        match player {
+            0 => &self.herpooles,
+            1 => &self.herpooles_two,
+            2 => &self.herpooles_three,
+            _ => &self.herpooles_four,
+        }
+    }
+}
+
+// How the characters and the arena look. Only the drawing reads it, the simulation and the
+// hitboxes are the same whatever the theme. Missing fields in a JSON theme fall back to
+// the classic look.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    pub palette: Palette,
+    // of the character strokes
+    pub line_width: f64,
+    pub herpooles: HerpoolesProportions,
+    pub zombie: ZombieProportions,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "classic".into(),
+            palette: Palette::default(),
+            line_width: 1.0,
+            herpooles: HerpoolesProportions::default(),
+            zombie: ZombieProportions::default(),
+        }
+    }
+}
+
+impl Theme {
+    // Names of the themes built into the game, the first one is the default.
+    pub const BUILT_IN: [&'static str; 3] = ["classic", "high-contrast", "halloween"];
+
+    pub fn built_in(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::default()),
+            "high-contrast" => Some(Theme {
+                name: name.into(),
+                palette: Palette {
+                    background: "black".into(),
+                    border: "white".into(),
+                    arena: "black".into(),
+                    world_bounds: "white".into(),
+                    herpooles: "lime".into(),
+                    herpooles_two: "cyan".into(),
+                    herpooles_three: "magenta".into(),
+                    herpooles_four: "white".into(),
+                    herpooles_dead: "red".into(),
+                    herpooles_flash: "black".into(),
+                    crown: "gold".into(),
+                    cape: "white".into(),
+                    zombie: "yellow".into(),
+                    zombie_dying: "magenta".into(),
+                },
+                line_width: 2.0,
+                ..Theme::default()
+            }),
+            "halloween" => Some(Theme {
+                name: name.into(),
+                palette: Palette {
+                    background: "#1b1024".into(),
+                    border: "darkorange".into(),
+                    arena: "#2b1836".into(),
+                    world_bounds: "darkorange".into(),
+                    herpooles: "darkorange".into(),
+                    herpooles_two: "mediumorchid".into(),
+                    herpooles_three: "tomato".into(),
+                    herpooles_four: "deepskyblue".into(),
+                    herpooles_dead: "crimson".into(),
+                    herpooles_flash: "white".into(),
+                    crown: "purple".into(),
+                    cape: "black".into(),
+                    zombie: "yellowgreen".into(),
+                    zombie_dying: "ivory".into(),
+                },
+                zombie: ZombieProportions {
+                    head_radius: 5.0,
+                    arm_width: 10.0,
+                    ..ZombieProportions::default()
+                },
+                ..Theme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Theme, String> {
+        let theme: Theme = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    // Sizes must be positive and colours given, anything else draws nothing or garbage.
+    fn validate(&self) -> Result<(), String> {
+        if self.line_width <= 0.0 {
+            return Err(format!("line_width must be positive: {}", self.line_width));
+        }
+        let h = &self.herpooles;
+        let z = &self.zombie;
+        let sizes = [
+            ("herpooles.scale", h.scale),
+            ("herpooles.head_radius", h.head_radius),
+            ("herpooles.body_height", h.body_height),
+            ("herpooles.leg_height", h.leg_height),
+            ("herpooles.arm_width", h.arm_width),
+            ("herpooles.crown_width", h.crown_width),
+            ("zombie.head_radius", z.head_radius),
+            ("zombie.body_height", z.body_height),
+            ("zombie.leg_height", z.leg_height),
+            ("zombie.arm_width", z.arm_width),
+        ];
+        if let Some((name, size)) = sizes.iter().find(|(_, size)| *size <= 0.0) {
+            return Err(format!("{} must be positive: {}", name, size));
+        }
+        if let Some((name, _)) = self
+            .palette
+            .colors()
+            .iter()
+            .find(|(_, color)| color.trim().is_empty())
+        {
+            return Err(format!("palette.{} has no colour", name));
+        }
+        Ok(())
+    }
+
+    // The whole theme is stored, so a theme loaded from JSON is kept as well.
+    pub fn load() -> Theme {
+        storage::load::<Theme>(STORAGE_KEY)
+            .filter(|theme| theme.validate().is_ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use crate::viewport::VIEW_WIDTH;
+
+// Seconds a toast stays up, the last of them fading out.
+const SHOWN: f64 = 4.0;
+const FADE: f64 = 0.5;
+
+// Short notices in a corner over the game, one at a time in the order they came.
+#[derive(Default)]
+pub struct Toasts {
+    waiting: VecDeque<String>,
+    // the one up and for how long it has been
+    current: Option<(String, f64)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: String) {
+        self.waiting.push_back(text);
+    }
+
+    // dt in seconds of real time, toasts go on while the game is paused
+    pub fn update(&mut self, dt: f64) {
+        if let Some((_, shown)) = &mut self.current {
+            *shown += dt;
+            if *shown >= SHOWN {
+                self.current = None;
+            }
+        }
+        if self.current.is_none() {
+            self.current = self.waiting.pop_front().map(|text| (text, 0.0));
+        }
+    }
+
+    pub fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        let Some((text, shown)) = &self.current else {
+            return;
+        };
+        let (width, height) = (320.0, 36.0);
+        let (x, y) = (VIEW_WIDTH - width - 10.0, 46.0);
+        ctx.save();
+        ctx.set_global_alpha(((SHOWN - shown) / FADE).min(1.0));
+        ctx.set_fill_style_str("rgba(0, 0, 0, 0.7)");
+        ctx.fill_rect(x, y, width, height);
+        ctx.set_fill_style_str("gold");
+        ctx.set_font("16px sans-serif");
+        ctx.set_text_align("center");
+        ctx.fill_text(text, x + width / 2.0, y + 23.0).unwrap();
+        ctx.restore();
+    }
+}
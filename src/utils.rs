@@ -1,6 +1,13 @@
+// The console only exists in the browser. Natively, when the simulation runs on a server
+// or in the tests, the message is dropped.
 #[allow(unused_macros)]
 macro_rules! log {
-    ($($t:tt)*) => (web_sys::console::log_1(&format!($($t)*).into()))
+    ($($t:tt)*) => {{
+        #[cfg(target_arch = "wasm32")]
+        web_sys::console::log_1(&format!($($t)*).into());
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = format_args!($($t)*);
+    }};
 }
 
 pub fn set_panic_hook() {
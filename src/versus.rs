@@ -0,0 +1,40 @@
+use crate::game::{Controller, Herpooles, Rules};
+
+// Versus: two heroes in the same arena, each one's poo kills zombies and stuns the other,
+// who is left standing for the zombies to reach. See game::Rules.
+
+// Levels a versus run lasts at most.
+pub const LEVELS: u16 = 3;
+// Seconds a hero hit by the other's poo can neither walk nor fire.
+pub const STUN: f64 = 1.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Winner(usize),
+    Draw,
+}
+
+// How a versus run ended, None while it goes on or for other runs. The last hero standing
+// wins. Once the last level is cleared, or if they fell together, the most zombies killed
+// wins, then the most rivals hit.
+pub fn outcome(heroes: &[Herpooles], controller: &Controller) -> Option<Outcome> {
+    if controller.rules() != Rules::Versus {
+        return None;
+    }
+    let living: Vec<&Herpooles> = heroes.iter().filter(|h| h.is_alive()).collect();
+    if let [last] = living[..] {
+        if heroes.len() > 1 {
+            return Some(Outcome::Winner(last.player));
+        }
+    }
+    let cleared = controller.level() >= LEVELS && controller.zombies().is_empty();
+    if !living.is_empty() && !cleared {
+        return None;
+    }
+    let best = heroes.iter().map(|h| (h.score, h.stuns)).max()?;
+    let mut winners = heroes.iter().filter(|h| (h.score, h.stuns) == best);
+    match (winners.next(), winners.next()) {
+        (Some(winner), None) => Some(Outcome::Winner(winner.player)),
+        _ => Some(Outcome::Draw),
+    }
+}
@@ -0,0 +1,38 @@
+use crate::window;
+
+// The canvas is drawn in these logical coordinates whatever its size on the page.
+pub const VIEW_WIDTH: f64 = 1000.0;
+pub const VIEW_HEIGHT: f64 = 800.0;
+
+// Largest size with the view's aspect ratio that fits in the available space.
+// The rest of the space is left as letterbox bars around the canvas.
+pub fn fit_size(available_width: f64, available_height: f64) -> (f64, f64) {
+    let scale = (available_width / VIEW_WIDTH).min(available_height / VIEW_HEIGHT);
+    let scale = scale.max(0.0);
+    (VIEW_WIDTH * scale, VIEW_HEIGHT * scale)
+}
+
+// Sizes the canvas to fit the window below the toolbar, with a backing store of
+// devicePixelRatio pixels per CSS pixel so it stays sharp on HiDPI screens, and scales
+// the context so the drawing code keeps working in logical coordinates.
+// Resizing the backing store clears the canvas and resets the context.
+pub fn fit(canvas: &web_sys::HtmlCanvasElement, ctx: &web_sys::CanvasRenderingContext2d) {
+    let window = window();
+    let available_width = window.inner_width().unwrap().as_f64().unwrap();
+    let available_height =
+        window.inner_height().unwrap().as_f64().unwrap() - f64::from(canvas.offset_top());
+    let (css_width, css_height) = fit_size(available_width - 16.0, available_height - 16.0);
+    let style = canvas.style();
+    style
+        .set_property("width", &format!("{}px", css_width))
+        .unwrap();
+    style
+        .set_property("height", &format!("{}px", css_height))
+        .unwrap();
+
+    let ratio = window.device_pixel_ratio();
+    canvas.set_width((css_width * ratio).round().max(1.0) as u32);
+    canvas.set_height((css_height * ratio).round().max(1.0) as u32);
+    let scale = f64::from(canvas.width()) / VIEW_WIDTH;
+    ctx.set_transform(scale, 0.0, 0.0, scale, 0.0, 0.0).unwrap();
+}
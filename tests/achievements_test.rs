@@ -0,0 +1,79 @@
+use herpooles::achievements::{Achievements, ACHIEVEMENTS};
+use herpooles::game::Event;
+use herpooles::stats::Stats;
+
+fn achievement(id: &str) -> &'static herpooles::achievements::Achievement {
+    ACHIEVEMENTS.iter().find(|a| a.id == id).unwrap()
+}
+
+fn ids(unlocked: Vec<&'static herpooles::achievements::Achievement>) -> Vec<&'static str> {
+    unlocked.iter().map(|a| a.id).collect()
+}
+
+#[test]
+fn kills_close_together_make_a_frenzy() {
+    let mut achievements = Achievements::default();
+    let mut stats = Stats::default();
+    stats.level_started(1);
+    let kill = [Event::ZombieKilled { x: 0.0, y: 0.0 }];
+    // one kill every half second never gets there
+    for i in 0..20 {
+        assert!(ids(achievements.update(&kill, &stats, i as f64 * 0.5, 0.0)).is_empty());
+    }
+    assert_eq!(achievements.progress(achievement("frenzy")), 4);
+    for i in 0..10 {
+        let unlocked = ids(achievements.update(&kill, &stats, 20.0 + i as f64 * 0.1, 7.0));
+        assert_eq!(unlocked.contains(&"frenzy"), i == 9);
+    }
+    assert!(achievements.is_unlocked("frenzy"));
+    assert_eq!(achievements.progress(achievement("frenzy")), 10);
+}
+
+#[test]
+fn levels_count_once_cleared() {
+    let mut achievements = Achievements::default();
+    let mut stats = Stats::default();
+    stats.level_started(1);
+    stats.touched_edge();
+    assert!(ids(achievements.update(&[], &stats, 1.0, 0.0)).is_empty());
+    stats.level_started(2);
+    // level 1 touched the edge, level 2 is not cleared yet
+    assert!(ids(achievements.update(&[], &stats, 2.0, 0.0)).is_empty());
+    assert_eq!(achievements.progress(achievement("veteran")), 2);
+    stats.level_started(3);
+    assert_eq!(
+        ids(achievements.update(&[], &stats, 3.0, 0.0)),
+        vec!["center-stage"]
+    );
+
+    for level in 4..=6 {
+        stats.level_started(level);
+        if level == 5 {
+            (0..61).for_each(|_| stats.fired());
+        }
+    }
+    assert!(!achievements
+        .update(&[], &stats, 4.0, 0.0)
+        .iter()
+        .any(|a| a.id == "sharpshooter"));
+}
+
+#[test]
+fn unlocks_and_progress_are_kept() {
+    let mut achievements = Achievements::default();
+    let mut stats = Stats::default();
+    for level in 1..=10 {
+        stats.level_started(level);
+        (0..3).for_each(|_| stats.fired());
+    }
+    let unlocked = ids(achievements.update(&[], &stats, 1.0, 42.0));
+    assert!(unlocked.contains(&"veteran"));
+    assert!(unlocked.contains(&"sharpshooter"));
+    achievements.update(&[Event::ZombieKilled { x: 0.0, y: 0.0 }], &stats, 2.0, 42.0);
+
+    let restored = Achievements::from_json(&achievements.to_json()).unwrap();
+    assert!(restored.is_unlocked("veteran"));
+    assert_eq!(restored.progress(achievement("frenzy")), 1);
+    assert_eq!(restored.unlocked_count(), achievements.unlocked_count());
+    assert!(Achievements::from_json(r#"{"version":0,"unlocked":{},"progress":{}}"#).is_err());
+}
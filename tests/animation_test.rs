@@ -0,0 +1,45 @@
+use approx::assert_abs_diff_eq;
+use herpooles::animation::{Animation, State, DEATH_DURATION};
+
+#[test]
+fn dying_ends_after_death_duration() {
+    let mut anim = Animation::default();
+    anim.die(10.0);
+    assert_eq!(anim.state, State::Dying { since: 10.0 });
+    anim.update(10.0 + DEATH_DURATION / 2.0);
+    assert!(!anim.is_done());
+    assert_abs_diff_eq!(anim.death_progress(10.0 + DEATH_DURATION / 2.0), 0.5);
+    anim.update(10.0 + DEATH_DURATION + 1e-9);
+    assert!(anim.is_done());
+}
+
+#[test]
+fn dead_characters_do_not_walk() {
+    let mut anim = Animation::default();
+    anim.walk(6.0); // a quarter of a stride
+    assert_abs_diff_eq!(anim.leg_swing(), 1.0, epsilon = 1e-6);
+    anim.die(0.0);
+    anim.walk(6.0);
+    assert_abs_diff_eq!(anim.leg_swing(), 1.0, epsilon = 1e-6);
+}
+
+#[test]
+fn flash_blinks_until_it_ends() {
+    let mut anim = Animation::default();
+    anim.flash(1.0, 1.0);
+    let blinks = (0..100)
+        .filter(|i| anim.flashing(1.0 + *i as f64 / 100.0))
+        .count();
+    assert!(blinks > 0 && blinks < 100);
+    assert!(!anim.flashing(2.0));
+}
+
+#[test]
+fn walk_frame_follows_the_walk_cycle() {
+    let mut anim = Animation::default();
+    assert_eq!(anim.walk_frame(8), 0);
+    anim.walk(6.0); // a quarter of a stride
+    assert_eq!(anim.walk_frame(8), 2);
+    anim.walk(17.0);
+    assert_eq!(anim.walk_frame(8), 7);
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use approx::assert_abs_diff_eq;
+use herpooles::audio::{self, Sound};
+use herpooles::camera::Camera;
+use herpooles::geometry::Point;
+
+#[test]
+fn pan_follows_screen_position() {
+    let view = Camera::new(1000.0, 800.0);
+    let listener = Point { x: 500.0, y: 400.0 };
+    let (left, _) = audio::spatialize(Point { x: 0.0, y: 400.0 }, listener, &view);
+    let (center, _) = audio::spatialize(Point { x: 500.0, y: 400.0 }, listener, &view);
+    let (right, _) = audio::spatialize(
+        Point {
+            x: 1000.0,
+            y: 400.0,
+        },
+        listener,
+        &view,
+    );
+    assert_abs_diff_eq!(left, -1.0, epsilon = f32::EPSILON);
+    assert_abs_diff_eq!(center, 0.0, epsilon = f32::EPSILON);
+    assert_abs_diff_eq!(right, 1.0, epsilon = f32::EPSILON);
+    // zombies walk in from outside the arena
+    let (offscreen, _) = audio::spatialize(
+        Point {
+            x: 1200.0,
+            y: -200.0,
+        },
+        listener,
+        &view,
+    );
+    assert_abs_diff_eq!(offscreen, 1.0, epsilon = f32::EPSILON);
+}
+
+#[test]
+fn pan_is_relative_to_the_scrolled_view() {
+    let mut view = Camera::new(1000.0, 800.0);
+    view.x = 500.0;
+    let listener = Point {
+        x: 1000.0,
+        y: 400.0,
+    };
+    let (center, _) = audio::spatialize(
+        Point {
+            x: 1000.0,
+            y: 400.0,
+        },
+        listener,
+        &view,
+    );
+    let (left, _) = audio::spatialize(Point { x: 500.0, y: 400.0 }, listener, &view);
+    assert_abs_diff_eq!(center, 0.0, epsilon = f32::EPSILON);
+    assert_abs_diff_eq!(left, -1.0, epsilon = f32::EPSILON);
+}
+
+#[test]
+fn gain_falls_off_with_distance() {
+    let view = Camera::new(1000.0, 800.0);
+    let listener = Point { x: 500.0, y: 400.0 };
+    let (_, near) = audio::spatialize(listener, listener, &view);
+    let (_, half) = audio::spatialize(Point { x: 500.0, y: 0.0 }, listener, &view);
+    let (_, far) = audio::spatialize(
+        Point {
+            x: 500.0,
+            y: 100000.0,
+        },
+        listener,
+        &view,
+    );
+    assert_abs_diff_eq!(near, 1.0, epsilon = f32::EPSILON);
+    assert_abs_diff_eq!(half, 0.5, epsilon = f32::EPSILON);
+    assert!(far > 0.0 && far < half);
+}
+
+#[test]
+fn the_manifest_lists_every_sound() {
+    let text = std::fs::read_to_string("resources/manifest.json").unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let sounds: HashMap<Sound, String> =
+        serde_json::from_value(manifest["sounds"].clone()).unwrap();
+    for sound in Sound::ALL {
+        assert!(Path::new(&sounds[&sound]).exists(), "{:?}", sound);
+    }
+}
@@ -0,0 +1,42 @@
+use approx::assert_abs_diff_eq;
+use herpooles::camera::Camera;
+use herpooles::game::World;
+use herpooles::geometry::Point;
+
+#[test]
+fn target_in_dead_zone_does_not_move_camera() {
+    let world = World::for_level(10);
+    let mut camera = Camera::new(1000.0, 800.0);
+    camera.follow(Point { x: 520.0, y: 390.0 }, &world, 1.0);
+    assert_abs_diff_eq!(camera.x, 0.0);
+    assert_abs_diff_eq!(camera.y, 0.0);
+}
+
+#[test]
+fn camera_catches_up_and_stays_in_world() {
+    let world = World::for_level(10);
+    let mut camera = Camera::new(1000.0, 800.0);
+    let target = Point {
+        x: world.width,
+        y: world.height,
+    };
+    let mut last_x = camera.x;
+    for _ in 0..600 {
+        camera.follow(target, &world, 1.0 / 60.0);
+        assert!(camera.x >= last_x);
+        last_x = camera.x;
+    }
+    assert_abs_diff_eq!(camera.x, world.width - camera.width, epsilon = 0.1);
+    assert_abs_diff_eq!(camera.y, world.height - camera.height, epsilon = 0.1);
+}
+
+#[test]
+fn world_as_big_as_view_keeps_camera_still() {
+    let world = World::for_level(1);
+    let mut camera = Camera::new(1000.0, 800.0);
+    camera.follow(Point { x: 990.0, y: 790.0 }, &world, 1.0);
+    assert_abs_diff_eq!(camera.x, 0.0);
+    assert_abs_diff_eq!(camera.y, 0.0);
+    let screen = camera.to_screen(Point { x: 990.0, y: 790.0 });
+    assert_abs_diff_eq!(screen.x, 990.0);
+}
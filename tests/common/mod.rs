@@ -0,0 +1,47 @@
+// Playing runs with scripted inputs, recorded the way the game records them. Shared by the
+// tests here and the server's, not every one of them uses all of it.
+#![allow(dead_code)]
+
+use herpooles::game::{self, Controller, Herpooles, Input};
+use herpooles::PressedKeys;
+
+// One tick: `act` gets every hero ready and tells the keys it holds.
+pub fn step(
+    heroes: &mut [Herpooles],
+    controller: &mut Controller,
+    mut act: impl FnMut(&mut Herpooles) -> PressedKeys,
+) {
+    let mut inputs = vec![];
+    for h in heroes.iter_mut() {
+        let held = act(h);
+        let input = Input::capture(h, held);
+        controller.replay.push(input);
+        inputs.push(input);
+    }
+    game::tick(heroes, &inputs, controller);
+}
+
+// Plays until the run is over.
+pub fn play(
+    controller: Controller,
+    act: impl FnMut(u64, &mut Herpooles) -> PressedKeys,
+) -> (Controller, Vec<Herpooles>) {
+    play_watched(controller, act, |_, _| {})
+}
+
+// Plays until the run is over, `watch` sees the run after every tick.
+pub fn play_watched(
+    mut controller: Controller,
+    mut act: impl FnMut(u64, &mut Herpooles) -> PressedKeys,
+    mut watch: impl FnMut(u64, &Controller),
+) -> (Controller, Vec<Herpooles>) {
+    let mut heroes = Herpooles::team(controller.replay.players);
+    for tick in 0u64.. {
+        step(&mut heroes, &mut controller, |h| act(tick, h));
+        watch(tick, &controller);
+        if game::run_over(&heroes, &controller) {
+            break;
+        }
+    }
+    (controller, heroes)
+}
@@ -0,0 +1,46 @@
+use herpooles::animation::State;
+use herpooles::game::{self, Controller, Herpooles, Rules};
+use herpooles::PressedKeys;
+
+fn nearest(heroes: &[Herpooles], x: f32, y: f32) -> f32 {
+    heroes
+        .iter()
+        .map(|h| ((h.x - x).powi(2) + (h.y - y).powi(2)).sqrt())
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[test]
+fn zombies_go_after_the_nearest_hero() {
+    let mut heroes = Herpooles::team(2);
+    heroes[0].x = 100.0;
+    heroes[1].x = 900.0;
+    let mut controller = Controller::with_seed(4, 2, Rules::Coop);
+    let before: Vec<f32> = controller
+        .zombies()
+        .iter()
+        .map(|z| nearest(&heroes, z.x, z.y))
+        .collect();
+    for _ in 0..60 {
+        game::update(&mut heroes, &[PressedKeys::default(); 2], &mut controller);
+    }
+    // 30 pixels closer, give or take the stagger
+    for (z, before) in controller.zombies().iter().zip(before) {
+        assert!(nearest(&heroes, z.x, z.y) < before - 20.0);
+    }
+}
+
+#[test]
+fn heroes_start_apart_and_the_run_ends_with_the_last_one() {
+    let mut heroes = Herpooles::team(2);
+    assert_eq!(heroes[0].player, 0);
+    assert_eq!(heroes[1].player, 1);
+    assert!(heroes[0].x < heroes[1].x);
+    let focus = game::focus(&heroes);
+    assert_eq!(focus.x, 500.0);
+
+    heroes[0].anim.state = State::Done;
+    assert!(!game::all_gone(&heroes));
+    heroes[1].anim.state = State::Done;
+    assert!(game::all_gone(&heroes));
+    assert_eq!(Herpooles::team(1)[0].x, Herpooles::new().x);
+}
@@ -0,0 +1,21 @@
+use herpooles::particles::{ParticleSystem, MAX_PARTICLES, SPLAT, ZOMBIE_DEATH};
+
+#[test]
+fn pool_is_capped() {
+    let mut particles = ParticleSystem::default();
+    for _ in 0..1000 {
+        particles.emit(&ZOMBIE_DEATH, 100.0, 100.0);
+    }
+    assert_eq!(particles.particles().count(), MAX_PARTICLES);
+}
+
+#[test]
+fn particles_fade_and_expire() {
+    let mut particles = ParticleSystem::default();
+    particles.emit(&SPLAT, 100.0, 100.0);
+    particles.update(0.1);
+    assert!(particles.particles().all(|p| p.alpha() < 1.0));
+    // longer than any splat particle lives
+    particles.update(1.0);
+    assert_eq!(particles.particles().count(), 0);
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use herpooles::game::{Controller, Direction, Herpooles, Input, Rules};
+use herpooles::replay::{simulate, Mode, Replay, ReplayError, Submission};
+use herpooles::PressedKeys;
+
+// Plays a run with every hero firing every second and turning every few, the second one
+// walking off to the left, until they are all gone.
+fn play(seed: u64, players: usize) -> (Controller, Vec<Herpooles>) {
+    play_on(Controller::with_seed(seed, players, Rules::Coop))
+}
+
+fn play_on(controller: Controller) -> (Controller, Vec<Herpooles>) {
+    let bearings = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+    common::play(controller, |tick, h| {
+        if tick.is_multiple_of(60) {
+            h.fire_poo();
+        }
+        h.bearing = bearings[(tick / 180) as usize % 4];
+        PressedKeys {
+            left: h.player == 1 && tick < 100,
+            ..PressedKeys::default()
+        }
+    })
+}
+
+fn submission(controller: &Controller) -> Submission {
+    Submission {
+        mode: Mode::for_players(controller.replay.players).unwrap(),
+        name: "tester".to_string(),
+        score: controller.score,
+        level: controller.level(),
+        replay: controller.replay.clone(),
+    }
+}
+
+#[test]
+fn inputs_come_back_as_recorded() {
+    let mut replay = Replay::new(7, 1);
+    let held = Input {
+        keys: PressedKeys {
+            up: true,
+            left: true,
+            ..PressedKeys::default()
+        },
+        fire: false,
+        bearing: Direction::West,
+    };
+    let fired = Input {
+        fire: true,
+        bearing: Direction::South,
+        ..held
+    };
+    for _ in 0..100 {
+        replay.push(held);
+    }
+    replay.push(fired);
+    assert_eq!(replay.ticks(), 101);
+    let inputs: Vec<_> = replay.inputs().collect();
+    assert_eq!(inputs[0], Some(held));
+    assert_eq!(inputs[99], Some(held));
+    assert_eq!(inputs[100], Some(fired));
+
+    let json = serde_json::to_string(&replay).unwrap();
+    assert_eq!(serde_json::from_str::<Replay>(&json).unwrap(), replay);
+}
+
+#[test]
+fn a_replay_cut_in_two_comes_back_whole() {
+    let (controller, _) = play(8, 2);
+    let replay = &controller.replay;
+    for tick in [0, 1, 99, replay.ticks()] {
+        let mut whole = Replay::new(replay.seed, replay.players);
+        for input in replay.inputs().take(tick as usize * 2) {
+            whole.push(input.unwrap());
+        }
+        let later = replay.since(tick);
+        assert!(later.same_run(replay));
+        assert_eq!(later.ticks(), replay.ticks() - tick);
+        whole.append(&later);
+        assert_eq!(&whole, replay);
+    }
+    assert!(!Replay::new(replay.seed + 1, 2).same_run(replay));
+}
+
+#[test]
+fn a_replay_plays_to_the_same_outcome() {
+    let (controller, _) = play(42, 1);
+    let outcome = simulate(&controller.replay).unwrap();
+    assert_eq!(outcome.score, controller.score);
+    assert_eq!(outcome.level, controller.level());
+    assert_eq!(outcome.ticks, controller.replay.ticks());
+    assert!(submission(&controller).verify().is_ok());
+}
+
+#[test]
+fn forged_runs_are_rejected() {
+    let (controller, heroes) = play(3, 1);
+
+    let mut forged = submission(&controller);
+    forged.score += 1;
+    assert!(forged.verify().is_err());
+
+    // the same inputs on another seed meet other zombies
+    let mut other_seed = controller.replay.clone();
+    other_seed.seed += 1;
+    assert_ne!(simulate(&other_seed), simulate(&controller.replay));
+
+    let mut longer = controller.replay.clone();
+    longer.push(Input::capture(&heroes[0], PressedKeys::default()));
+    assert_eq!(simulate(&longer), Err(ReplayError::TrailingInput));
+
+    let mut unfinished = Replay::new(3, 1);
+    unfinished.push(Input::capture(&Herpooles::new(), PressedKeys::default()));
+    assert_eq!(simulate(&unfinished), Err(ReplayError::Unfinished));
+}
+
+#[test]
+fn coop_runs_play_again_for_two() {
+    let (controller, heroes) = play(8, 2);
+    assert_eq!(
+        controller.replay.ticks() * 2,
+        controller.replay.inputs().count() as u64
+    );
+    let outcome = simulate(&controller.replay).unwrap();
+    assert_eq!(outcome.score, controller.score);
+    assert_eq!(heroes[0].score + heroes[1].score, controller.score);
+    assert!(submission(&controller).verify().is_ok());
+
+    let mut classic = submission(&controller);
+    classic.mode = Mode::Classic;
+    assert!(classic.verify().is_err());
+
+    // the second hero's input of the tick is missing
+    let mut cut = Replay::new(8, 2);
+    cut.push(Input::capture(&heroes[0], PressedKeys::default()));
+    assert_eq!(simulate(&cut), Err(ReplayError::BadInput));
+    assert_eq!(simulate(&Replay::new(8, 3)), Err(ReplayError::BadPlayers));
+}
+
+#[test]
+fn a_set_horde_plays_again_off_the_scoreboard() {
+    let mut controller = Controller::with_seed(3, 1, Rules::Coop);
+    controller.crowd(3);
+    assert_eq!(controller.zombies().len(), 3);
+    let (controller, _) = play_on(controller);
+    assert!(controller.level() > 1);
+    let outcome = simulate(&controller.replay).unwrap();
+    assert_eq!(outcome.score, controller.score);
+    assert_eq!(outcome.level, controller.level());
+    assert!(submission(&controller).verify().is_err());
+
+    // a new run keeps it
+    let mut restarted = controller.clone();
+    restarted.restart(1, Rules::Coop);
+    assert_eq!(restarted.zombies().len(), 3);
+    assert_eq!(restarted.replay.horde, Some(3));
+}
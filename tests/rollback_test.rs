@@ -0,0 +1,153 @@
+use std::ops::RangeInclusive;
+
+use herpooles::game::{Direction, Input};
+use herpooles::rollback::{Frame, InputMessage, Rollback, MAX_ROLLBACK};
+use herpooles::PressedKeys;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// What every player does, walking about and firing now and then.
+fn script(player: usize, tick: u64) -> Input {
+    let phase = (tick / (40 + 13 * player as u64)) % 4;
+    Input {
+        keys: PressedKeys {
+            left: phase == 0,
+            up: phase == 1,
+            right: phase == 2,
+            down: phase == 3 && tick.is_multiple_of(3),
+        },
+        fire: (tick + 7 * player as u64).is_multiple_of(50),
+        bearing: [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ][(tick / 90 + player as u64) as usize % 4],
+    }
+}
+
+// Messages between the peers, each one late by a random number of ticks or lost.
+struct Network {
+    rng: ChaCha8Rng,
+    latency: RangeInclusive<u64>,
+    loss: f64,
+    // (tick it arrives on, peer it is for, message)
+    in_flight: Vec<(u64, usize, InputMessage)>,
+}
+
+impl Network {
+    fn new(latency: RangeInclusive<u64>, loss: f64) -> Network {
+        Network {
+            rng: ChaCha8Rng::seed_from_u64(5),
+            latency,
+            loss,
+            in_flight: vec![],
+        }
+    }
+
+    fn broadcast(&mut self, now: u64, peers: usize, message: InputMessage) {
+        for to in (0..peers).filter(|to| *to != message.player) {
+            if self.rng.gen_bool(self.loss) {
+                continue;
+            }
+            let arrives = now + self.rng.gen_range(self.latency.clone());
+            self.in_flight.push((arrives, to, message.clone()));
+        }
+    }
+
+    fn deliver(&mut self, now: u64, peers: &mut [Rollback]) {
+        let (due, later) = self.in_flight.drain(..).partition(|(at, _, _)| *at <= now);
+        self.in_flight = later;
+        for (_, to, message) in due {
+            peers[to].receive(&message);
+        }
+    }
+}
+
+// Plays `ticks` ticks on every peer over the network, then lets the messages go around
+// until every peer has every input.
+fn play(players: usize, ticks: u64, network: &mut Network) -> Vec<Rollback> {
+    let mut peers: Vec<Rollback> = (0..players)
+        .map(|p| Rollback::new(9, players, p).unwrap())
+        .collect();
+    for now in 0u64.. {
+        for peer in peers.iter_mut() {
+            if peer.tick() < ticks {
+                peer.advance(script(peer.player, peer.tick()));
+            }
+        }
+        for peer in &peers {
+            network.broadcast(now, players, peer.message());
+        }
+        network.deliver(now, &mut peers);
+        if peers.iter().all(|p| p.confirmed() == ticks) {
+            break;
+        }
+        assert!(now < ticks * 10, "the peers never caught up");
+    }
+    peers
+}
+
+// The same game on one machine, every input known in time.
+fn reference(players: usize, ticks: u64) -> String {
+    let mut frame = Frame::new(9, players);
+    for tick in 0..ticks {
+        let inputs: Vec<Input> = (0..players).map(|p| script(p, tick)).collect();
+        frame.step(&inputs);
+    }
+    serde_json::to_string(&frame).unwrap()
+}
+
+#[test]
+fn peers_end_up_in_the_same_game_despite_latency_and_loss() {
+    let mut network = Network::new(2..=8, 0.2);
+    let peers = play(2, 900, &mut network);
+    let expected = reference(2, 900);
+    for peer in &peers {
+        assert_eq!(serde_json::to_string(peer.frame()).unwrap(), expected);
+        assert!(peer.resimulated > 0);
+    }
+
+    let mut network = Network::new(0..=12, 0.35);
+    let peers = play(3, 600, &mut network);
+    let expected = reference(3, 600);
+    for peer in &peers {
+        assert_eq!(serde_json::to_string(peer.frame()).unwrap(), expected);
+    }
+}
+
+#[test]
+fn a_silent_peer_stalls_the_others() {
+    let mut first = Rollback::new(1, 2, 0).unwrap();
+    let mut second = Rollback::new(1, 2, 1).unwrap();
+    let played = (0..MAX_ROLLBACK + 10)
+        .filter(|tick| first.advance(script(0, *tick)).is_some())
+        .count() as u64;
+    assert_eq!(played, MAX_ROLLBACK);
+
+    for tick in 0..20 {
+        second.advance(script(1, tick));
+    }
+    first.receive(&second.message());
+    assert_eq!(first.confirmed(), 20);
+    assert!(first.advance(script(0, first.tick())).is_some());
+}
+
+#[test]
+fn seats_and_acks_outside_the_game_are_not_believed() {
+    assert!(Rollback::new(1, 2, 2).is_none());
+
+    let mut first = Rollback::new(1, 2, 0).unwrap();
+    let mut second = Rollback::new(1, 2, 1).unwrap();
+    for tick in 0..5 {
+        first.advance(script(0, tick));
+        second.advance(script(1, tick));
+    }
+    let mut message = second.message();
+    message.acks[0] = 1000;
+    first.receive(&message);
+    // every input counts as acked, none is sent again
+    let message = first.message();
+    assert_eq!(message.first, 5);
+    assert!(message.inputs.is_empty());
+}
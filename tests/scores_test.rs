@@ -0,0 +1,49 @@
+use herpooles::scores::{Entry, HighScores, MAX_ENTRIES};
+
+fn entry(name: &str, score: u32) -> Entry {
+    Entry {
+        name: name.to_string(),
+        score,
+        level: 1,
+        duration: 60.0,
+        date: 0.0,
+    }
+}
+
+#[test]
+fn keeps_the_best_runs_in_order() {
+    let mut scores = HighScores::default();
+    assert!(!scores.qualifies(0));
+    for score in 1..=MAX_ENTRIES as u32 {
+        assert!(scores.insert(entry("a", score)).is_some());
+    }
+    assert_eq!(scores.entries().len(), MAX_ENTRIES);
+    assert_eq!(scores.entries()[0].score, MAX_ENTRIES as u32);
+    // the lowest score no longer makes it
+    assert!(!scores.qualifies(1));
+    assert_eq!(scores.insert(entry("b", 1)), None);
+    assert_eq!(scores.insert(entry("c", 5)), Some(MAX_ENTRIES - 4));
+    assert_eq!(scores.entries().len(), MAX_ENTRIES);
+    assert_eq!(scores.entries().last().unwrap().score, 2);
+    assert_eq!(scores.last_name, "c");
+}
+
+#[test]
+fn older_runs_keep_their_place_on_ties() {
+    let mut scores = HighScores::default();
+    scores.insert(entry("first", 7));
+    assert_eq!(scores.insert(entry("second", 7)), Some(1));
+    assert_eq!(scores.entries()[0].name, "first");
+}
+
+#[test]
+fn round_trips_and_rejects_other_versions() {
+    let mut scores = HighScores::default();
+    scores.insert(entry("a", 3));
+    scores.insert(entry("b", 9));
+    let json = scores.to_json();
+    assert_eq!(HighScores::from_json(&json).unwrap(), scores);
+
+    let future = json.replace("\"version\":1", "\"version\":2");
+    assert!(HighScores::from_json(&future).is_err());
+}
@@ -0,0 +1,202 @@
+use herpooles::game::Rules;
+use herpooles::screen::{key_action, Action, Screen, Transition, FIRE_KEYS, LEVEL_COUNTDOWN};
+
+#[test]
+fn title_starts_a_run_with_the_level_intro() {
+    let mut screen = Screen::Title;
+    assert_eq!(screen.handle(Action::Pause), Transition::None);
+    assert_eq!(screen, Screen::Title);
+    assert_eq!(
+        screen.handle(Action::Confirm),
+        Transition::Start {
+            players: 1,
+            rules: Rules::Coop
+        }
+    );
+    assert_eq!(
+        screen,
+        Screen::LevelIntro {
+            level: 1,
+            remaining: LEVEL_COUNTDOWN
+        }
+    );
+    screen.update(LEVEL_COUNTDOWN / 2.0);
+    assert!(!screen.is_playing());
+    screen.update(LEVEL_COUNTDOWN / 2.0);
+    assert!(screen.is_playing());
+}
+
+#[test]
+fn two_players_start_from_the_title_only() {
+    let mut screen = Screen::Title;
+    assert_eq!(
+        screen.handle(Action::TwoPlayers),
+        Transition::Start {
+            players: 2,
+            rules: Rules::Coop
+        }
+    );
+    assert!(matches!(screen, Screen::LevelIntro { level: 1, .. }));
+    assert_eq!(screen.handle(Action::TwoPlayers), Transition::None);
+}
+
+#[test]
+fn versus_starts_from_the_title_only() {
+    let mut screen = Screen::Title;
+    assert_eq!(
+        screen.handle(Action::Versus),
+        Transition::Start {
+            players: 2,
+            rules: Rules::Versus
+        }
+    );
+    assert!(matches!(screen, Screen::LevelIntro { level: 1, .. }));
+    assert_eq!(screen.handle(Action::Versus), Transition::None);
+}
+
+#[test]
+fn continuing_a_saved_run_starts_paused() {
+    let mut screen = Screen::Title;
+    assert_eq!(screen.handle(Action::Continue), Transition::Resume);
+    assert!(!screen.is_playing());
+    assert_eq!(screen.handle(Action::Confirm), Transition::None);
+    assert!(screen.is_playing());
+    // only from the title
+    assert_eq!(screen.handle(Action::Continue), Transition::None);
+    assert!(screen.is_playing());
+}
+
+#[test]
+fn pause_resumes_where_it_left_off() {
+    let mut screen = Screen::LevelIntro {
+        level: 3,
+        remaining: 1.0,
+    };
+    screen.pause();
+    // the countdown does not run while paused
+    screen.update(5.0);
+    assert!(matches!(screen, Screen::Paused { .. }));
+    // pausing again does not resume
+    screen.pause();
+    assert_eq!(screen.handle(Action::Pause), Transition::None);
+    assert_eq!(
+        screen,
+        Screen::LevelIntro {
+            level: 3,
+            remaining: 1.0
+        }
+    );
+}
+
+#[test]
+fn level_change_and_game_over_only_while_playing() {
+    let mut screen = Screen::Playing;
+    screen.level_started(2);
+    assert!(matches!(screen, Screen::LevelIntro { level: 2, .. }));
+    screen.game_over(10);
+    assert!(matches!(screen, Screen::LevelIntro { .. }));
+
+    let mut screen = Screen::Playing;
+    screen.game_over(10);
+    assert_eq!(
+        screen,
+        Screen::GameOver {
+            score: 10,
+            rank: None
+        }
+    );
+    assert_eq!(screen.handle(Action::Pause), Transition::None);
+    assert_eq!(screen.handle(Action::Quit), Transition::None);
+    assert_eq!(screen, Screen::Title);
+}
+
+#[test]
+fn game_over_plays_again() {
+    for action in [Action::Confirm, Action::Restart] {
+        let mut screen = Screen::GameOver {
+            score: 3,
+            rank: Some(0),
+        };
+        assert_eq!(screen.handle(action), Transition::NewRun);
+        assert!(matches!(screen, Screen::LevelIntro { level: 1, .. }));
+    }
+}
+
+#[test]
+fn name_entry_takes_every_key_until_confirmed() {
+    let mut screen = Screen::Playing;
+    screen.enter_name(42, "bob");
+    for action in [
+        Action::Erase,
+        Action::Type('R'),
+        Action::Type('q'),
+        Action::Pause,
+        Action::Quit,
+    ] {
+        assert_eq!(screen.handle(action), Transition::None);
+    }
+    assert_eq!(
+        screen,
+        Screen::EnterName {
+            score: 42,
+            name: "boRq".to_string()
+        }
+    );
+    assert_eq!(
+        screen.handle(Action::Confirm),
+        Transition::NameEntered("boRq".to_string())
+    );
+    screen.set_rank(Some(2));
+    assert_eq!(
+        screen,
+        Screen::GameOver {
+            score: 42,
+            rank: Some(2)
+        }
+    );
+}
+
+#[test]
+fn empty_names_are_anonymous() {
+    let mut screen = Screen::EnterName {
+        score: 1,
+        name: "  ".to_string(),
+    };
+    assert_eq!(
+        screen.handle(Action::Confirm),
+        Transition::NameEntered("anonymous".to_string())
+    );
+}
+
+#[test]
+fn fire_keys_are_no_screen_keys() {
+    // firing must not resume, confirm a name or play again
+    for key in FIRE_KEYS {
+        assert_eq!(key_action(key), None);
+    }
+    // enter
+    assert_eq!(key_action(13), Some(Action::Confirm));
+}
+
+#[test]
+fn online_runs_only_pause_and_quit() {
+    let mut screen = Screen::Playing;
+    assert_eq!(screen.handle_online(Action::Restart), Transition::None);
+    assert_eq!(screen, Screen::Playing);
+    assert_eq!(screen.handle_online(Action::Pause), Transition::None);
+    assert!(matches!(screen, Screen::Paused { .. }));
+    assert_eq!(screen.handle_online(Action::Restart), Transition::None);
+    assert!(matches!(screen, Screen::Paused { .. }));
+    assert_eq!(screen.handle_online(Action::Confirm), Transition::None);
+    assert_eq!(screen, Screen::Playing);
+
+    let mut screen = Screen::GameOver {
+        score: 10,
+        rank: None,
+    };
+    assert_eq!(screen.handle_online(Action::Confirm), Transition::None);
+    assert_eq!(screen.handle_online(Action::Restart), Transition::None);
+    assert!(matches!(screen, Screen::GameOver { .. }));
+    assert_eq!(screen.handle_online(Action::Quit), Transition::None);
+    assert_eq!(screen, Screen::Title);
+}
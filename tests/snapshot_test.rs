@@ -0,0 +1,59 @@
+mod common;
+
+use herpooles::game::{Controller, Herpooles, Rules};
+use herpooles::snapshot::Snapshot;
+use herpooles::PressedKeys;
+
+fn step(heroes: &mut [Herpooles], controller: &mut Controller, tick: u64) {
+    common::step(heroes, controller, |h| {
+        if tick.is_multiple_of(40) {
+            h.fire_poo();
+        }
+        PressedKeys {
+            left: tick % 200 < 100,
+            right: tick % 200 >= 100,
+            ..PressedKeys::default()
+        }
+    });
+}
+
+#[test]
+fn a_restored_run_carries_on_exactly() {
+    let mut h = Herpooles::team(2);
+    let mut controller = Controller::with_seed(11, 2, Rules::Coop);
+    for tick in 0..300 {
+        step(&mut h, &mut controller, tick);
+    }
+    let json = Snapshot::take(&h, &controller).to_json();
+    let (mut restored_h, mut restored_controller) = Snapshot::from_json(&json).unwrap().restore();
+    assert_eq!(
+        Snapshot::take(&restored_h, &restored_controller).to_json(),
+        json
+    );
+
+    // the zombies wander randomly, so this only holds if the generator came back too
+    for tick in 300..900 {
+        step(&mut h, &mut controller, tick);
+        step(&mut restored_h, &mut restored_controller, tick);
+    }
+    assert_eq!(
+        Snapshot::take(&restored_h, &restored_controller).to_json(),
+        Snapshot::take(&h, &controller).to_json()
+    );
+}
+
+#[test]
+fn other_versions_and_garbage_are_rejected() {
+    let json = Snapshot::take(
+        &Herpooles::team(1),
+        &Controller::with_seed(1, 1, Rules::Coop),
+    )
+    .to_json();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["version"] = 99.into();
+    assert!(Snapshot::from_json(&value.to_string()).is_err());
+    value.as_object_mut().unwrap().remove("version");
+    assert!(Snapshot::from_json(&value.to_string()).is_err());
+    assert!(Snapshot::from_json("{\"version\":1}").is_err());
+    assert!(Snapshot::from_json("not json").is_err());
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use herpooles::game::{self, Controller, Direction, Herpooles, Rules};
+use herpooles::spectate::{Broadcast, Spectator, Ticks, MAX_BEHIND};
+use herpooles::PressedKeys;
+
+// A run with the heroes firing now and then and the second one walking off to the left.
+// Calls sent with the run so far every few ticks and at the end.
+fn play(
+    seed: u64,
+    players: usize,
+    mut sent: impl FnMut(&Controller),
+) -> (Controller, Vec<Herpooles>) {
+    let controller = Controller::with_seed(seed, players, Rules::Coop);
+    let (controller, heroes) = common::play_watched(
+        controller,
+        |tick, h| {
+            if (tick + 17 * h.player as u64).is_multiple_of(40) {
+                h.fire_poo();
+            }
+            h.bearing = [Direction::North, Direction::West][(tick / 200) as usize % 2];
+            PressedKeys {
+                left: h.player == 1 && tick < 100,
+                ..PressedKeys::default()
+            }
+        },
+        |tick, c| {
+            if tick.is_multiple_of(7) {
+                sent(c);
+            }
+        },
+    );
+    sent(&controller);
+    (controller, heroes)
+}
+
+fn same_game(spectator: &Spectator, controller: &Controller, heroes: &[Herpooles]) {
+    let frame = spectator.frame().unwrap();
+    assert_eq!(
+        serde_json::to_string(&frame.heroes).unwrap(),
+        serde_json::to_string(heroes).unwrap()
+    );
+    assert_eq!(frame.controller.score, controller.score);
+    assert_eq!(frame.controller.level(), controller.level());
+    assert_eq!(
+        serde_json::to_string(frame.controller.zombies()).unwrap(),
+        serde_json::to_string(controller.zombies()).unwrap()
+    );
+}
+
+#[test]
+fn watchers_play_the_broadcast_run_again() {
+    let mut broadcast = Broadcast::default();
+    let mut messages: Vec<Ticks> = vec![];
+    let (controller, heroes) = play(12, 2, |c| messages.extend(broadcast.ticks(&c.replay)));
+    assert_eq!(messages[0].first, 0);
+    assert!(messages
+        .windows(2)
+        .all(|m| m[1].first == m[0].first + m[0].replay.ticks()));
+    // nothing new, nothing sent
+    assert_eq!(broadcast.ticks(&controller.replay), None);
+
+    // a watcher from the start, a frame of time after every message
+    let mut spectator = Spectator::default();
+    assert!(spectator.frame().is_none());
+    for ticks in &messages {
+        spectator.receive(ticks);
+        spectator.advance(ticks.replay.ticks() as f64 * game::TICK);
+        assert!(spectator.behind() <= 1);
+    }
+    spectator.advance(game::TICK);
+    assert_eq!(spectator.behind(), 0);
+    assert!(spectator.is_over());
+    same_game(&spectator, &controller, &heroes);
+}
+
+#[test]
+fn late_watchers_catch_up_at_once() {
+    let mut broadcast = Broadcast::default();
+    let (controller, heroes) = play(5, 1, |_| {});
+    let whole = broadcast.ticks(&controller.replay).unwrap();
+
+    let mut spectator = Spectator::default();
+    // ticks that do not follow the run so far are dropped
+    spectator.receive(&Ticks {
+        first: 10,
+        ..whole.clone()
+    });
+    assert!(spectator.frame().is_none());
+    spectator.receive(&whole);
+    assert_eq!(spectator.behind(), controller.replay.ticks());
+    spectator.advance(0.0);
+    assert_eq!(spectator.behind(), MAX_BEHIND);
+    for _ in 0..MAX_BEHIND {
+        assert!(!spectator.is_over());
+        spectator.advance(game::TICK);
+    }
+    assert_eq!(spectator.behind(), 0);
+    same_game(&spectator, &controller, &heroes);
+}
+
+#[test]
+fn another_run_goes_out_from_its_first_tick() {
+    let mut broadcast = Broadcast::default();
+    let (first, _) = play(1, 1, |_| {});
+    let (second, heroes) = play(2, 1, |_| {});
+    let mut spectator = Spectator::default();
+    spectator.receive(&broadcast.ticks(&first.replay).unwrap());
+    spectator.advance(0.0);
+
+    let restarted = broadcast.ticks(&second.replay).unwrap();
+    assert_eq!(restarted.first, 0);
+    spectator.receive(&restarted);
+    while spectator.behind() > 0 {
+        spectator.advance(game::TICK);
+    }
+    same_game(&spectator, &second, &heroes);
+}
@@ -0,0 +1,73 @@
+use approx::assert_abs_diff_eq;
+use herpooles::game::{self, Controller, Herpooles, Rules, TICK};
+use herpooles::stats::{Cause, Stats};
+use herpooles::PressedKeys;
+
+#[test]
+fn counts_shots_hits_and_levels() {
+    let mut stats = Stats::default();
+    assert_eq!(stats.accuracy(), None);
+    stats.level_started(1);
+    stats.fired();
+    stats.fired();
+    stats.hit();
+    stats.tick(0.5);
+    stats.level_started(2);
+    stats.fired();
+    stats.fired();
+    stats.tick(0.25);
+    assert_abs_diff_eq!(stats.accuracy().unwrap(), 0.25);
+    assert_eq!(stats.levels[0].kills, 1);
+    assert_abs_diff_eq!(stats.levels[0].duration, 0.5);
+    assert_eq!(stats.levels[1].kills, 0);
+    assert_abs_diff_eq!(stats.levels[1].duration, 0.25);
+
+    stats.zombie_at(30.0);
+    stats.zombie_at(12.0);
+    stats.zombie_at(40.0);
+    assert_eq!(stats.closest_call, Some(12.0));
+
+    stats.died(0, Cause::Zombie, 3.0, 10.0, 20.0);
+    let death = stats.deaths[0];
+    assert_eq!(death.level, 2);
+    assert_abs_diff_eq!(death.x, 10.0);
+    // in co-op every hero's death is kept
+    stats.died(1, Cause::Zombie, 4.0, 50.0, 60.0);
+    assert_eq!(stats.deaths.len(), 2);
+    assert_eq!((stats.deaths[0].player, stats.deaths[1].player), (0, 1));
+
+    let json = stats.to_json();
+    assert!(json.contains("\"cause\": \"zombie\""));
+    assert_eq!(serde_json::from_str::<Stats>(&json).unwrap(), stats);
+}
+
+#[test]
+fn a_run_is_tracked_until_death() {
+    let mut heroes = Herpooles::team(1);
+    let mut controller = Controller::with_seed(5, 1, Rules::Coop);
+    let keys = PressedKeys {
+        right: true,
+        ..PressedKeys::default()
+    };
+    let mut ticks = 0;
+    while !game::all_gone(&heroes) {
+        if ticks % 30 == 0 {
+            heroes[0].fire_poo();
+        }
+        game::update(&mut heroes, &[keys], &mut controller);
+        controller.check();
+        ticks += 1;
+    }
+    let stats = &controller.stats;
+    assert_eq!(stats.hits, controller.score);
+    assert!(stats.shots >= stats.hits);
+    // two pixels a tick until the edge of the world
+    assert!(stats.distance > 0.0 && stats.distance <= 2.0 * ticks as f32);
+    assert!(stats.closest_call.unwrap() < game::HIT_RADIUS);
+    assert_eq!(stats.deaths.len(), 1);
+    let death = stats.deaths[0];
+    assert_eq!(death.level, controller.level());
+    assert_abs_diff_eq!(death.x, heroes[0].x);
+    let alive: f64 = stats.levels.iter().map(|l| l.duration).sum();
+    assert_abs_diff_eq!(alive, death.time, epsilon = TICK);
+}
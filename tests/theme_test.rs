@@ -0,0 +1,54 @@
+use approx::assert_abs_diff_eq;
+use herpooles::theme::Theme;
+
+#[test]
+fn built_in_themes_are_named_after_themselves() {
+    for name in Theme::BUILT_IN {
+        let theme = Theme::built_in(name).unwrap();
+        assert_eq!(theme.name, name);
+    }
+    assert_eq!(Theme::built_in(Theme::BUILT_IN[0]), Some(Theme::default()));
+    assert_eq!(Theme::built_in("nope"), None);
+}
+
+#[test]
+fn json_theme_falls_back_to_classic() {
+    let theme = Theme::from_json(
+        r#"{"name": "winter", "palette": {"zombie": "steelblue"}, "herpooles": {"scale": 0.3}}"#,
+    )
+    .unwrap();
+    let classic = Theme::default();
+    assert_eq!(theme.name, "winter");
+    assert_eq!(theme.palette.zombie, "steelblue");
+    assert_eq!(theme.palette.cape, classic.palette.cape);
+    assert_abs_diff_eq!(theme.herpooles.scale, 0.3);
+    assert_eq!(theme.herpooles.arm_width, classic.herpooles.arm_width);
+    assert_eq!(theme.zombie, classic.zombie);
+}
+
+#[test]
+fn shipped_theme_parses() {
+    let json = std::fs::read_to_string("resources/themes/winter.json").unwrap();
+    assert_eq!(Theme::from_json(&json).unwrap().name, "winter");
+}
+
+#[test]
+fn theme_round_trips_through_json() {
+    let theme = Theme::built_in("halloween").unwrap();
+    let json = serde_json::to_string(&theme).unwrap();
+    assert_eq!(Theme::from_json(&json).unwrap(), theme);
+}
+
+#[test]
+fn themes_that_cannot_be_drawn_are_refused() {
+    assert!(Theme::from_json(r#"{"line_width": 0}"#).is_err());
+    assert!(Theme::from_json(r#"{"line_width": -1.5}"#).is_err());
+    assert!(Theme::from_json(r#"{"herpooles": {"scale": 0}}"#).is_err());
+    assert!(Theme::from_json(r#"{"zombie": {"arm_width": -8}}"#).is_err());
+    assert!(Theme::from_json(r#"{"palette": {"cape": ""}}"#).is_err());
+    assert!(Theme::from_json(r#"{"palette": {"zombie": "  "}}"#).is_err());
+    for name in Theme::BUILT_IN {
+        let json = serde_json::to_string(&Theme::built_in(name).unwrap()).unwrap();
+        assert!(Theme::from_json(&json).is_ok());
+    }
+}
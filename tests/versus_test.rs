@@ -0,0 +1,119 @@
+mod common;
+
+use herpooles::game::{self, Controller, Direction, Herpooles, Rules};
+use herpooles::replay::simulate;
+use herpooles::versus::{self, Outcome, LEVELS};
+use herpooles::PressedKeys;
+
+fn versus_controller(seed: u64) -> Controller {
+    Controller::with_seed(seed, 2, Rules::Versus)
+}
+
+// The same run with the last level cleared.
+fn cleared_last_level(controller: &Controller) -> Controller {
+    let mut value = serde_json::to_value(controller).unwrap();
+    value["level"] = LEVELS.into();
+    value["zombies"] = serde_json::json!([]);
+    serde_json::from_value(value).unwrap()
+}
+
+// The first hero throws at the second, who walks to the left all along.
+#[test]
+fn poo_stuns_the_rival() {
+    let mut heroes = Herpooles::team(2);
+    let mut controller = versus_controller(3);
+    heroes[0].bearing = Direction::East;
+    let keys = [
+        PressedKeys::default(),
+        PressedKeys {
+            left: true,
+            ..PressedKeys::default()
+        },
+    ];
+    let mut hit = None;
+    for tick in 0..90 {
+        // once reloaded
+        if tick == 30 {
+            heroes[0].fire_poo();
+        }
+        game::update(&mut heroes, &keys, &mut controller);
+        if hit.is_none() && heroes[0].stuns > 0 {
+            hit = Some((tick, heroes[1].x));
+        }
+    }
+    let (tick, x) = hit.expect("the poo never hit");
+    assert!(heroes[1].is_alive());
+    assert_eq!(heroes[0].stuns, 1);
+    assert!(heroes[1].is_stunned(controller.time));
+    // a second of standing still, it lasts to the last tick
+    assert!((30..90).contains(&tick) && tick + 60 >= 90);
+    assert_eq!(heroes[1].x, x);
+    assert!(heroes[0].poo().is_empty());
+}
+
+#[test]
+fn co_op_poo_flies_through_the_other_hero() {
+    let mut heroes = Herpooles::team(2);
+    let mut controller = Controller::with_seed(3, 2, Rules::Coop);
+    heroes[0].bearing = Direction::East;
+    for tick in 0..90 {
+        if tick == 30 {
+            heroes[0].fire_poo();
+        }
+        game::update(&mut heroes, &[PressedKeys::default(); 2], &mut controller);
+    }
+    assert!(!heroes[1].is_stunned(controller.time));
+    assert_eq!(heroes[0].stuns, 0);
+}
+
+#[test]
+fn last_standing_wins_else_the_best_score() {
+    let mut heroes = Herpooles::team(2);
+    let controller = versus_controller(3);
+    assert_eq!(versus::outcome(&heroes, &controller), None);
+    assert_eq!(
+        versus::outcome(&heroes, &Controller::with_seed(3, 2, Rules::Coop)),
+        None
+    );
+
+    // the zombies reached the second
+    heroes[1].anim.die(controller.time);
+    assert_eq!(
+        versus::outcome(&heroes, &controller),
+        Some(Outcome::Winner(0))
+    );
+
+    // both standing when the last level is cleared
+    let mut heroes = Herpooles::team(2);
+    let controller = cleared_last_level(&versus_controller(3));
+    assert_eq!(versus::outcome(&heroes, &controller), Some(Outcome::Draw));
+    heroes[1].score = 4;
+    assert_eq!(
+        versus::outcome(&heroes, &controller),
+        Some(Outcome::Winner(1))
+    );
+    heroes[0].score = 4;
+    heroes[0].stuns = 1;
+    assert_eq!(
+        versus::outcome(&heroes, &controller),
+        Some(Outcome::Winner(0))
+    );
+}
+
+// Both throw at each other, the replay plays to the same end.
+#[test]
+fn versus_replays_play_again() {
+    let (controller, heroes) = common::play(versus_controller(8), |tick, h| {
+        h.bearing = [Direction::East, Direction::West][h.player];
+        if (tick + 20 * h.player as u64).is_multiple_of(45) {
+            h.fire_poo();
+        }
+        PressedKeys::default()
+    });
+    assert_eq!(controller.rules(), Rules::Versus);
+    assert_eq!(controller.replay.rules, Rules::Versus);
+    assert!(versus::outcome(&heroes, &controller).is_some());
+    let outcome = simulate(&controller.replay).unwrap();
+    assert_eq!(outcome.score, controller.score);
+    assert_eq!(outcome.ticks, controller.replay.ticks());
+}
@@ -0,0 +1,14 @@
+use approx::assert_abs_diff_eq;
+use herpooles::viewport::{fit_size, VIEW_HEIGHT, VIEW_WIDTH};
+
+#[test]
+fn fit_keeps_aspect_ratio() {
+    // wide window, bars left and right
+    let (w, h) = fit_size(2000.0, 400.0);
+    assert_abs_diff_eq!(h, 400.0);
+    assert_abs_diff_eq!(w / h, VIEW_WIDTH / VIEW_HEIGHT);
+    // narrow phone, bars above and below
+    let (w, h) = fit_size(375.0, 700.0);
+    assert_abs_diff_eq!(w, 375.0);
+    assert_abs_diff_eq!(w / h, VIEW_WIDTH / VIEW_HEIGHT);
+}